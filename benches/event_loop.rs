@@ -1,6 +1,7 @@
 use std::path::Path;
 
 use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main, Throughput};
+use outbreak_sim::routing::Router;
 use outbreak_sim::shared::get_time_steps_per_day;
 
 // TODO When global param modification is added, make sure setup is done in bench for consistency
@@ -11,20 +12,17 @@ fn bench_event_loop(c: &mut Criterion) {
     {
         let sim = outbreak_sim::SimBuilder::new(&Path::new(model_dir), model_name)
             .load_fast_graph_from_disk(true)
-            .build();
+            .build()
+            .unwrap();
         let num_agents = sim.agents.num_agents as u64;
         group.throughput(Throughput::Elements(num_agents));
         group.bench_with_input(BenchmarkId::new(format!("One Day: {} time-steps", get_time_steps_per_day()), model_name), &num_agents, |b, _| {
             b.iter_batched(
-                || {
-                    (sim.clone(),
-                    fast_paths::create_calculator(&sim.fast_graph))
-                },
-                |(mut sim, mut path_calculator)| {
+                || sim.clone(),
+                |mut sim| {
+                    let mut router = Router::new(&sim.commute_cache, &mut sim.congestion, &sim.transit_stop_index, &sim.fast_graph);
                     for time_step in 0..get_time_steps_per_day() {
-                        sim.events.update(time_step, &mut sim.agents, &mut sim.containers,
-                                          &sim.transit_granular_grid, &sim.fast_graph,
-                                          &mut path_calculator)
+                        sim.events.update(time_step, &mut sim.agents, &mut sim.containers, &mut router, &sim.interventions)
                     }
                 },
                 BatchSize::LargeInput);
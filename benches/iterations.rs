@@ -1,10 +1,13 @@
 use criterion::{BenchmarkId, black_box, Criterion, criterion_group, criterion_main, Throughput};
 
+// only the size of these is benchmarked, so the fields are never read
+#[allow(dead_code)]
 struct U32Bool {
     val: u32,
     active: bool,
 }
 
+#[allow(dead_code)]
 struct U64Bool {
     val: u64,
     active: bool,
@@ -16,7 +19,7 @@ fn bench_u32_loop(c: &mut Criterion) {
     for size in [100u32, 1_000u32, 10_000u32, 1_000_000u32, 10_000_000u32].iter() {
         let range_vec: Vec<u32> = (0..*size).collect();
         group.throughput(Throughput::Elements(*size as u64));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx + 1); }));
         });
     }
@@ -28,7 +31,7 @@ fn bench_u32_bool_loop(c: &mut Criterion) {
     for size in [100u32, 1_000u32, 10_000u32, 1_000_000u32, 10_000_000u32].iter() {
         let range_vec: Vec<U32Bool> = (0..*size).map(|idx| U32Bool { val: idx, active: false }).collect();
         group.throughput(Throughput::Elements(*size as u64));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx); }));
         });
     }
@@ -38,9 +41,9 @@ fn bench_u32_bool_loop(c: &mut Criterion) {
 fn bench_u32_option_loop(c: &mut Criterion) {
     let mut group = c.benchmark_group("u32 w/ Option Looping");
     for size in [100u32, 1_000u32, 10_000u32, 1_000_000u32, 10_000_000u32].iter() {
-        let range_vec: Vec<Option<u32>> = (0..*size).map(|idx| Some(idx)).collect();
+        let range_vec: Vec<Option<u32>> = (0..*size).map(Some).collect();
         group.throughput(Throughput::Elements(*size as u64));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx); }));
         });
     }
@@ -52,7 +55,7 @@ fn bench_u64_loop(c: &mut Criterion) {
     for size in [100u64, 1_000u64, 10_000u64, 1_000_000u64, 10_000_000u64].iter() {
         let range_vec: Vec<u64> = (0..*size).collect();
         group.throughput(Throughput::Elements(*size));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx); }));
         });
     }
@@ -64,7 +67,7 @@ fn bench_u64_bool_loop(c: &mut Criterion) {
     for size in [100u64, 1_000u64, 10_000u64, 1_000_000u64, 10_000_000u64].iter() {
         let range_vec: Vec<U64Bool> = (0..*size).map(|idx| U64Bool { val: idx, active: false }).collect();
         group.throughput(Throughput::Elements(*size));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx); }));
         });
     }
@@ -74,9 +77,9 @@ fn bench_u64_bool_loop(c: &mut Criterion) {
 fn bench_u64_option_loop(c: &mut Criterion) {
     let mut group = c.benchmark_group("u64 w/ Option Looping");
     for size in [100u64, 1_000u64, 10_000u64, 1_000_000u64, 10_000_000u64].iter() {
-        let range_vec: Vec<Option<u64>> = (0..*size).map(|idx| Some(idx)).collect();
-        group.throughput(Throughput::Elements(*size as u64));
-        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, &size| {
+        let range_vec: Vec<Option<u64>> = (0..*size).map(Some).collect();
+        group.throughput(Throughput::Elements(*size));
+        group.bench_with_input(BenchmarkId::from_parameter(size), size, |b, _| {
             b.iter(|| range_vec.iter().for_each(|idx| { black_box(idx); }));
         });
     }
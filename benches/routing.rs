@@ -8,9 +8,9 @@ use rand::distributions::Standard;
 use rand::seq::SliceRandom;
 use rayon::prelude::*;
 
-use outbreak_sim::{get_root_as_model, read_buffer, Vec2};
+use outbreak_sim::{read_buffer, root_as_model, Vec2};
 use outbreak_sim::disease::MixingStrategy;
-use outbreak_sim::routing::{calculate_direct_commute_time, DirectRoutingType, distance_f32, GranularGrid, nodes_to_granular_grid, sample_nearby_from_grid};
+use outbreak_sim::routing::{calculate_direct_commute_time, DirectRoutingType, distance_f32, GranularGrid, nodes_to_granular_grid, sample_nearby_from_grid, SpatialIndex};
 use outbreak_sim::Sim;
 
 #[inline]
@@ -42,7 +42,7 @@ fn choose_and_calc_workplace_transit_commute(agent_positions: &[Vec2], workplace
                 let src_node = sample_nearby_from_grid(transit_node_grid, (household_pos.y(), household_pos.x()), 8_000.0, 1, &mut rng).unwrap();
                 let dest_node = sample_nearby_from_grid(transit_node_grid, (workplace_pos.y(), workplace_pos.x()), 8_000.0, 1, &mut rng).unwrap();
 
-                path_calculator.calc_path(&fast_graph, src_node[0], dest_node[0]);
+                path_calculator.calc_path(fast_graph, src_node[0], dest_node[0]);
             });
 }
 
@@ -60,11 +60,11 @@ fn bench_build_granular_grid(c: &mut Criterion) {
 
     for &model_name in ["isle_of_dogs", "greater_manchester"].iter() {
         for rows in [50u32, 100u32, 200u32].iter() {
-            let bytes = read_buffer(("python/synthetic_environments/examples/".to_string() + model_name + ".txt").as_ref());
-            let model = get_root_as_model(&bytes);
+            let bytes = read_buffer(("python/synthetic_environments/examples/".to_string() + model_name + ".txt").as_ref()).unwrap();
+            let model = root_as_model(&bytes).unwrap();
             group.bench_with_input(
                 BenchmarkId::new(model_name, rows), rows,
-                |b, rows| b.iter(|| nodes_to_granular_grid(&model.transit_graph(), &model.bounds(), *rows)),
+                |b, rows| b.iter(|| nodes_to_granular_grid(&model.transit_graph(), model.bounds(), *rows)),
             );
         }
     }
@@ -77,7 +77,8 @@ fn bench_choose_nearby_nodes(c: &mut Criterion) {
     for &model_name in ["isle_of_dogs", "greater_manchester"].iter() {
         let sim = outbreak_sim::SimBuilder::new(&Path::new("python/synthetic_environments/examples"), model_name)
             .load_fast_graph_from_disk(true)
-            .build();
+            .build()
+            .unwrap();
         let agent_positions: Vec<Vec2> = sim.agents.household_container.iter()
             .zip(sim.agents.occupational_container.iter())
             .filter_map(|(&household_idx, occupational_idx)| {
@@ -102,6 +103,32 @@ fn bench_choose_nearby_nodes(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_nearest_stops(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Nearest Transit Stops");
+
+    for &model_name in ["isle_of_dogs", "greater_manchester"].iter() {
+        let sim = outbreak_sim::SimBuilder::new(&Path::new("python/synthetic_environments/examples"), model_name)
+            .load_fast_graph_from_disk(true)
+            .build()
+            .unwrap();
+        let agent_positions: Vec<Vec2> = sim.agents.household_container.iter()
+            .map(|&household_idx| sim.containers.get(household_idx).unwrap().pos)
+            .collect();
+        let stop_grid = GranularGrid::from_points(
+            sim.transit_node_positions.iter().enumerate().map(|(idx, &pos)| (pos, idx)), 100, &sim.bounds);
+
+        group.bench_function(
+            BenchmarkId::new("KdTree", model_name),
+            |b| b.iter(|| agent_positions.iter().for_each(|&pos| { sim.transit_stop_index.nearest(pos, 5, 3_500.0); })),
+        );
+        group.bench_function(
+            BenchmarkId::new("GranularGrid", model_name),
+            |b| b.iter(|| agent_positions.iter().for_each(|&pos| { stop_grid.nearest(pos, 5, 3_500.0); })),
+        );
+    }
+    group.finish();
+}
+
 // TODO Convert this from a batch test to benchmark an individual routing scenario
 fn bench_choose_and_route_transit_commutes(c: &mut Criterion) {
     let mut group = c.benchmark_group("Commute Routing by Transit with Node Choosing");
@@ -109,7 +136,8 @@ fn bench_choose_and_route_transit_commutes(c: &mut Criterion) {
     for &model_name in ["isle_of_dogs", "greater_manchester"].iter() {
         let sim = outbreak_sim::SimBuilder::new(&Path::new("python/synthetic_environments/examples"), model_name)
             .load_fast_graph_from_disk(true)
-            .build();
+            .build()
+            .unwrap();
         let (agent_positions, workplace_positions): (Vec<Vec2>, Vec<Vec2>) = sim.agents.household_container.iter()
             .zip(sim.agents.occupational_container.iter())
             .filter_map(|(&household_idx, occupational_idx)| {
@@ -143,7 +171,8 @@ fn bench_fast_graph_route(c: &mut Criterion) {
     {
         let sim = outbreak_sim::SimBuilder::new(&Path::new(model_dir), model_name)
             .load_fast_graph_from_disk(true)
-            .build();
+            .build()
+            .unwrap();
         let mut rng = thread_rng();
         let mut node_pairs: Vec<(usize, usize)> = sim.agents.household_container.iter()
             .zip(sim.agents.occupational_container.iter())
@@ -165,12 +194,12 @@ fn bench_fast_graph_route(c: &mut Criterion) {
         let mut node_pairs_iter = node_pairs.iter();
 
         let mut next = || {
-            return if let Some(pair) = node_pairs_iter.next() {
+            if let Some(pair) = node_pairs_iter.next() {
                 pair
             } else {
                 node_pairs_iter = node_pairs.iter();
                 node_pairs_iter.next().unwrap()
-            };
+            }
         };
 
         group.bench_function(
@@ -195,15 +224,13 @@ fn bench_direct_commute_calc(c: &mut Criterion) {
     for &model_name in ["isle_of_dogs", "greater_manchester"].iter() {
         let sim = outbreak_sim::SimBuilder::new(&Path::new("python/synthetic_environments/examples"), model_name)
             .load_fast_graph_from_disk(true)
-            .build();
+            .build()
+            .unwrap();
         let (household_containers, occupational_containers): (Vec<NonMaxU64>, Vec<NonMaxU64>) = sim.agents.household_container.iter()
             .zip(sim.agents.occupational_container.iter())
             .filter_map(|(&household_container_idx, &occupational_container_idx)| {
-                if let Some(occupational_idx) = occupational_container_idx {
-                    Some((NonMaxU64::new(household_container_idx).unwrap(), occupational_idx))
-                } else {
-                    None
-                }
+                occupational_container_idx
+                    .map(|occupational_idx| (NonMaxU64::new(household_container_idx).unwrap(), occupational_idx))
             }).unzip();
 
         group.bench_function(
@@ -236,5 +263,5 @@ fn bench_distance(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_build_granular_grid, bench_choose_nearby_nodes, bench_fast_graph_route, bench_choose_and_route_transit_commutes, bench_direct_commute_calc, bench_distance);
+criterion_group!(benches, bench_build_granular_grid, bench_choose_nearby_nodes, bench_nearest_stops, bench_fast_graph_route, bench_choose_and_route_transit_commutes, bench_direct_commute_calc, bench_distance);
 criterion_main!(benches);
\ No newline at end of file
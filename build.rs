@@ -0,0 +1,19 @@
+use std::process::Command;
+
+/// Embeds the commit the simulation was built from in `OUTBREAK_SIM_GIT_HASH`, for the concluding
+/// report, or "unknown" if it isn't built from a git repository
+fn main() {
+    let git_hash = git(&["rev-parse", "--short", "HEAD"]).unwrap_or_else(|| "unknown".to_owned());
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"]).is_some_and(|status| !status.is_empty());
+    println!("cargo:rustc-env=OUTBREAK_SIM_GIT_HASH={}{}", git_hash, if dirty { "-dirty" } else { "" });
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8(output.stdout).ok().map(|output| output.trim().to_owned())
+}
@@ -0,0 +1,94 @@
+"""
+Builds the infection tree from the transmission log of a simulation run (`transmissions.csv`, written when running with
+`--record-transmissions`), and summarises it with generation intervals, the distribution of secondary cases, and the
+infections in each setting.
+
+usage: python infection_tree.py <path to transmissions.csv> [--time-steps-per-day 48] [--out-dir <dir>]
+"""
+import argparse
+from pathlib import Path
+
+import networkx as nx
+import pandas as pd
+
+
+def read_transmissions(path):
+    return pd.read_csv(path, dtype={"infector": "Int64", "container_idx": "Int64"})
+
+
+def build_infection_tree(transmissions):
+    """
+    A directed graph with an edge from each infector to those they infected. Infections that aren't attributed to an
+    agent (seeded, imported, or only attributed to a container) are the roots of the tree.
+    """
+    tree = nx.DiGraph()
+    for row in transmissions.itertuples(index=False):
+        tree.add_node(row.infectee, time_step=row.time_step, source=row.source, container_idx=row.container_idx,
+                      container_type=row.container_type)
+    for row in transmissions.dropna(subset=["infector"]).itertuples(index=False):
+        tree.add_edge(int(row.infector), row.infectee)
+    return tree
+
+
+def generation_intervals(tree, time_steps_per_day):
+    """
+    The days between each infector's and infectee's infections, where both were recorded. As containers are updated
+    lazily, infections are found up to a container's next update and intervals are approximate.
+    """
+    intervals = []
+    for infector, infectee in tree.edges:
+        infector_time_step = tree.nodes[infector].get("time_step")
+        if infector_time_step is not None:
+            intervals.append((tree.nodes[infectee]["time_step"] - infector_time_step) / time_steps_per_day)
+    return pd.Series(intervals, name="generation_interval_days", dtype=float)
+
+
+def secondary_cases(tree):
+    """ The number of agents each infected agent went on to infect """
+    return pd.Series({node: tree.out_degree(node) for node, data in tree.nodes(data=True) if "time_step" in data},
+                     name="secondary_cases", dtype=int)
+
+
+def infections_by_setting(transmissions):
+    """
+    The infections within each type of container, along with the number of containers they happened in and the mean
+    infections per container with any, as a measure of each setting's attack rate
+    """
+    in_containers = transmissions.dropna(subset=["container_type"])
+    per_container = in_containers.groupby(["container_type", "container_idx"]).size()
+    return pd.DataFrame({
+        "infections": in_containers.groupby("container_type").size(),
+        "containers": per_container.groupby(level="container_type").size(),
+        "mean_infections_per_container": per_container.groupby(level="container_type").mean(),
+    })
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__, formatter_class=argparse.RawDescriptionHelpFormatter)
+    parser.add_argument("transmissions", type=Path)
+    parser.add_argument("--time-steps-per-day", type=int, default=48)
+    parser.add_argument("--out-dir", type=Path, help="where to write the summaries, defaults to the log's directory")
+    args = parser.parse_args()
+    out_dir = args.out_dir or args.transmissions.parent
+    out_dir.mkdir(parents=True, exist_ok=True)
+
+    transmissions = read_transmissions(args.transmissions)
+    tree = build_infection_tree(transmissions)
+
+    intervals = generation_intervals(tree, args.time_steps_per_day)
+    cases = secondary_cases(tree)
+    settings = infections_by_setting(transmissions)
+
+    nx.write_edgelist(tree, out_dir / "infection_tree.edgelist", data=False)
+    intervals.to_csv(out_dir / "generation_intervals.csv", index=False)
+    cases.to_csv(out_dir / "secondary_cases.csv", index_label="agent_idx")
+    settings.to_csv(out_dir / "infections_by_setting.csv")
+
+    print(f"{len(transmissions)} infections, {len(tree.edges)} attributed to an infector")
+    print(f"mean generation interval: {intervals.mean():.2f} days")
+    print(f"mean secondary cases: {cases.mean():.2f}, dispersion (variance / mean): {cases.var() / cases.mean():.2f}")
+    print(settings)
+
+
+if __name__ == "__main__":
+    main()
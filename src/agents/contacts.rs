@@ -0,0 +1,133 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::containers::Containers;
+use crate::disease::MixingStrategy;
+use crate::shared::types::TimeStep;
+
+/// A finished stay of an agent in a container
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Visit {
+    pub agent_idx: u32,
+    pub container_idx: u64,
+    pub arrival_time_step: TimeStep,
+    pub departure_time_step: TimeStep,
+}
+
+impl Visit {
+    #[inline]
+    fn overlaps(&self, start: TimeStep, end: TimeStep) -> bool {
+        self.arrival_time_step < end && self.departure_time_step > start
+    }
+}
+
+/// # Contact History
+/// The recent visits to every container, so the agents who shared a container with someone can be
+/// found for contact tracing. Visits that ended more than `lookback_time_steps` ago are discarded.
+#[derive(Clone)]
+pub struct ContactHistory {
+    lookback_time_steps: TimeStep,
+    /// The container each agent is in and the time step they arrived, `None` while in transit
+    arrivals: Vec<Option<(u64, TimeStep)>>,
+    container_visits: HashMap<u64, VecDeque<Visit>>,
+    agent_visits: Vec<VecDeque<Visit>>,
+}
+
+impl ContactHistory {
+    pub fn new(current_containers: impl Iterator<Item=Option<u64>>, time_step: TimeStep, lookback_time_steps: TimeStep) -> Self {
+        let arrivals: Vec<Option<(u64, TimeStep)>> = current_containers
+            .map(|container_idx| container_idx.map(|container_idx| (container_idx, time_step)))
+            .collect();
+        ContactHistory {
+            lookback_time_steps,
+            agent_visits: vec![VecDeque::new(); arrivals.len()],
+            arrivals,
+            container_visits: HashMap::new(),
+        }
+    }
+
+    #[inline]
+    pub fn lookback_time_steps(&self) -> TimeStep {
+        self.lookback_time_steps
+    }
+
+    #[inline]
+    pub fn arrive(&mut self, agent_idx: u32, container_idx: u64, time_step: TimeStep) {
+        self.arrivals[agent_idx as usize] = Some((container_idx, time_step));
+    }
+
+    pub fn depart(&mut self, agent_idx: u32, time_step: TimeStep) {
+        if let Some((container_idx, arrival_time_step)) = self.arrivals[agent_idx as usize].take() {
+            let visit = Visit { agent_idx, container_idx, arrival_time_step, departure_time_step: time_step };
+            self.container_visits.entry(container_idx).or_default().push_back(visit);
+            self.agent_visits[agent_idx as usize].push_back(visit);
+        }
+    }
+
+    /// Discards the visits that ended before the lookback period
+    pub fn prune(&mut self, time_step: TimeStep) {
+        let cutoff = time_step.saturating_sub(self.lookback_time_steps);
+        let is_old = |visit: &Visit| visit.departure_time_step < cutoff;
+        for visits in self.container_visits.values_mut().chain(self.agent_visits.iter_mut()) {
+            while visits.front().is_some_and(is_old) {
+                visits.pop_front();
+            }
+        }
+        self.container_visits.retain(|_, visits| !visits.is_empty());
+    }
+
+    /// The agents who shared a container with the given agent at some point since `since_time_step`,
+    /// including those still with them
+    pub fn contacts<M>(&self, agent_idx: u32, since_time_step: TimeStep, time_step: TimeStep, containers: &Containers<M>) -> Vec<u32>
+        where M: MixingStrategy
+    {
+        let open_visit = self.arrivals[agent_idx as usize].map(|(container_idx, arrival_time_step)| {
+            Visit { agent_idx, container_idx, arrival_time_step, departure_time_step: time_step }
+        });
+
+        let mut contacts = Vec::new();
+        for visit in self.agent_visits[agent_idx as usize].iter().chain(open_visit.iter())
+            .filter(|visit| visit.departure_time_step >= since_time_step)
+        {
+            let start = visit.arrival_time_step.max(since_time_step);
+            let end = visit.departure_time_step.max(start + 1);
+            if let Some(visits) = self.container_visits.get(&visit.container_idx) {
+                contacts.extend(visits.iter().filter(|other| other.overlaps(start, end)).map(|other| other.agent_idx));
+            }
+            if let Some(container) = containers.get(visit.container_idx) {
+                contacts.extend(container.inhabitants.iter().copied().filter(|&other| {
+                    self.arrivals[other as usize].is_some_and(|(_, arrival_time_step)| arrival_time_step < end)
+                }));
+            }
+        }
+
+        contacts.sort_unstable();
+        contacts.dedup();
+        contacts.retain(|&other| other != agent_idx);
+        contacts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visits_overlap_and_prune() {
+        let mut history = ContactHistory::new(vec![Some(0), Some(0), Some(1)].into_iter(), 0, 10);
+        history.depart(0, 5);
+        history.arrive(0, 2, 6);
+        history.depart(1, 8);
+        history.depart(0, 9);
+
+        let visits = &history.container_visits[&0];
+        assert_eq!(visits.len(), 2);
+        assert!(visits[0].overlaps(4, 6));
+        assert!(!visits[0].overlaps(5, 8));
+        assert_eq!(history.agent_visits[0].len(), 2);
+
+        history.prune(19);
+        assert!(!history.container_visits.contains_key(&0));
+        assert_eq!(history.agent_visits[0].len(), 1);
+        assert_eq!(history.agent_visits[0][0].container_idx, 2);
+    }
+}
@@ -0,0 +1,168 @@
+use nonmax::NonMaxU64;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::contacts::ContactHistory;
+use crate::routing::RoutingType;
+use crate::shared::types::TimeStep;
+
+/// Where an agent is, either inside a container or travelling between two, unless they've been
+/// removed from the simulation, e.g. having died
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Location {
+    InContainer(u64),
+    InTransit(Journey),
+    Removed,
+}
+
+/// A trip between two containers that's underway
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Journey {
+    pub from_container_idx: NonMaxU64,
+    pub to_container_idx: NonMaxU64,
+    pub routing_type: RoutingType,
+    pub departure_time_step: TimeStep,
+    pub arrival_time_step: TimeStep,
+}
+
+impl Journey {
+    /// How far through the journey the agent is at a given time step, in the range [0, 1]
+    #[inline]
+    pub fn progress(&self, time_step: TimeStep) -> f32 {
+        if self.arrival_time_step <= self.departure_time_step {
+            return 1.0;
+        }
+        let elapsed = time_step.saturating_sub(self.departure_time_step) as f32;
+        (elapsed / (self.arrival_time_step - self.departure_time_step) as f32).min(1.0)
+    }
+}
+
+/// A change in an agent's location, as recorded in the trajectory log
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct TrajectoryEntry {
+    pub time_step: TimeStep,
+    pub agent_idx: u32,
+    /// The container entered, or left when `in_transit` is true
+    pub container_idx: u64,
+    pub in_transit: bool,
+    /// The container being travelled to, if in transit
+    pub to_container_idx: Option<u64>,
+    /// The time step the agent will arrive, if in transit
+    pub arrival_time_step: Option<TimeStep>,
+}
+
+/// The current location of every agent, along with an optional log of every change in location
+/// and an optional history of recent visits to containers
+#[derive(Clone)]
+pub struct AgentLocations {
+    current: Vec<Location>,
+    trajectory_log: Option<Vec<TrajectoryEntry>>,
+    contact_history: Option<ContactHistory>,
+}
+
+impl AgentLocations {
+    pub fn new(household_container: &[u64]) -> Self {
+        AgentLocations {
+            current: household_container.iter().map(|&container_idx| Location::InContainer(container_idx)).collect(),
+            trajectory_log: None,
+            contact_history: None,
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, agent_idx: u32) -> Location {
+        self.current[agent_idx as usize]
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.current.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.current.is_empty() }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=&Location> {
+        self.current.iter()
+    }
+
+    #[inline]
+    pub fn set(&mut self, agent_idx: u32, location: Location, time_step: TimeStep) {
+        self.current[agent_idx as usize] = location;
+
+        if let Some(history) = self.contact_history.as_mut() {
+            history.depart(agent_idx, time_step);
+            if let Location::InContainer(container_idx) = location {
+                history.arrive(agent_idx, container_idx, time_step);
+            }
+        }
+
+        if let Some(log) = self.trajectory_log.as_mut() {
+            match location {
+                Location::InContainer(container_idx) => {
+                    log.push(TrajectoryEntry {
+                        time_step,
+                        agent_idx,
+                        container_idx,
+                        in_transit: false,
+                        to_container_idx: None,
+                        arrival_time_step: None,
+                    });
+                }
+                Location::InTransit(journey) => {
+                    log.push(TrajectoryEntry {
+                        time_step,
+                        agent_idx,
+                        container_idx: journey.from_container_idx.get(),
+                        in_transit: true,
+                        to_container_idx: Some(journey.to_container_idx.get()),
+                        arrival_time_step: Some(journey.arrival_time_step),
+                    });
+                }
+                // the agent's last entry is the last place they were
+                Location::Removed => {}
+            }
+        }
+    }
+
+    /// Starts or stops recording changes of location, stopping discards the existing log
+    pub fn record_trajectories(&mut self, record: bool) {
+        if !record {
+            self.trajectory_log = None;
+        } else if self.trajectory_log.is_none() {
+            self.trajectory_log = Some(Vec::new());
+        }
+    }
+
+    #[inline]
+    pub fn trajectory_log(&self) -> Option<&[TrajectoryEntry]> {
+        self.trajectory_log.as_deref()
+    }
+
+    /// Starts recording the visits to containers from the given time step, keeping those that ended
+    /// within the last `lookback_time_steps`, or stops recording and discards them if `None`
+    pub fn record_contacts(&mut self, lookback_time_steps: Option<TimeStep>, time_step: TimeStep) {
+        self.contact_history = lookback_time_steps.map(|lookback_time_steps| {
+            let current_containers = self.current.iter().map(|location| match location {
+                Location::InContainer(container_idx) => { Some(*container_idx) }
+                Location::InTransit(_) | Location::Removed => { None }
+            });
+            ContactHistory::new(current_containers, time_step, lookback_time_steps)
+        });
+    }
+
+    #[inline]
+    pub fn contact_history(&self) -> Option<&ContactHistory> {
+        self.contact_history.as_ref()
+    }
+
+    #[inline]
+    pub fn contact_history_mut(&mut self) -> Option<&mut ContactHistory> {
+        self.contact_history.as_mut()
+    }
+
+    /// Takes the entries recorded so far, leaving the log empty but still recording
+    pub fn drain_trajectory_log(&mut self) -> Vec<TrajectoryEntry> {
+        self.trajectory_log.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
@@ -1,18 +1,32 @@
+use std::collections::HashMap;
+
 use nonmax::NonMaxU64;
-use rand::thread_rng;
+use rand::Rng;
 
-use crate::containers::Containers;
+pub use crate::agents::contacts::{ContactHistory, Visit};
+pub use crate::agents::location::{AgentLocations, Journey, Location, TrajectoryEntry};
+use crate::containers::{Containers, ContainerType};
 use crate::disease;
-use crate::disease::{DiseaseStatus, MixingStrategy};
+use crate::disease::{DiseaseStatus, MixingStrategy, TransmissionLog};
 use crate::flatbuffer::Model;
+use crate::shared::{RngStream, seeded_rng};
+
+mod contacts;
+mod location;
 
 #[derive(Clone)]
 pub struct Agents {
     pub num_agents: u32,
+    pub ages: Vec<u8>,
     pub household_container: Vec<u64>,
     pub occupational_container: Vec<Option<NonMaxU64>>,
     // workplace or school
     pub disease_statuses: Vec<DiseaseStatus>,
+    /// How willing each agent is to wear a mask in [0, 1), those with the highest propensities
+    /// being the ones to comply with a mask mandate
+    pub mask_propensity: Vec<f32>,
+    pub locations: AgentLocations,
+    pub transmission_log: TransmissionLog,
 }
 
 impl Agents {
@@ -22,28 +36,85 @@ impl Agents {
         let household_indices = model.agents().household_index();
         let workplace_indices = model.agents().workplace_index();
 
-        let mut rng = thread_rng();
+        let mut rng = seeded_rng(RngStream::Agents, &[]);
         let num_agents = household_indices.len() as u32;
 
-        let (household_container, workplace_container) = household_indices.iter().zip(workplace_indices.iter())
+        let (household_container, workplace_container): (Vec<u64>, Vec<Option<NonMaxU64>>) = household_indices.iter().zip(workplace_indices.iter())
             .enumerate().map(|(agent_idx, (household_idx, workplace_idx))| {
             let household_container_idx = containers.get_household_idx(household_idx);
+            // Everyone starts the simulation at home, those with a workplace leave when their first Travel event is handled
+            containers.push_inhabitant_no_update(household_container_idx, agent_idx as u32);
             let workplace_container_idx = match workplace_idx {
-                u32::MAX => {
-                    // TODO Update this, super hacky right now as people without workplaces don't have Event schedules so need to manually be placed in a container
-                    containers.push_inhabitant_no_update(household_container_idx, agent_idx as u32);
-                    None
-                }
+                u32::MAX => { None }
                 _ => { NonMaxU64::new(containers.get_workplace_idx(workplace_idx)) }
             };
             (household_container_idx, workplace_container_idx)
         }).unzip();
 
+        let ages = model.agents().age().to_vec();
+        classify_schools(&ages, &workplace_container, containers);
+
         Agents {
             num_agents,
+            ages,
+            locations: AgentLocations::new(&household_container),
             household_container,
             occupational_container: workplace_container,
             disease_statuses: disease::construct_disease_status_array(num_agents, &mut rng),
+            mask_propensity: (0..num_agents).map(|_| rng.gen::<f32>()).collect(),
+            transmission_log: TransmissionLog::default(),
+        }
+    }
+}
+
+impl Agents {
+    /// Susceptible agents of the given ages, living in the given households and without an
+    /// occupation, for tests that don't need a model
+    #[cfg(test)]
+    pub(crate) fn with_households(ages: Vec<u8>, household_container: Vec<u64>) -> Agents {
+        let num_agents = ages.len() as u32;
+        Agents {
+            num_agents,
+            ages,
+            locations: AgentLocations::new(&household_container),
+            occupational_container: vec![None; household_container.len()],
+            household_container,
+            disease_statuses: vec![DiseaseStatus::susceptible(); num_agents as usize],
+            mask_propensity: vec![0.0; num_agents as usize],
+            transmission_log: TransmissionLog::default(),
         }
     }
-}
\ No newline at end of file
+
+    /// Reassigns a share of the agents with an occupation to work in the container chosen for them
+    pub(crate) fn reassign_workers<F>(&mut self, share: f32, mut choose_container: F)
+        where F: FnMut(u32) -> u64
+    {
+        let mut rng = seeded_rng(RngStream::HealthcareWorkers, &[]);
+        for (agent_idx, occupational_container) in self.occupational_container.iter_mut().enumerate() {
+            if occupational_container.is_some() && rng.gen::<f32>() < share {
+                *occupational_container = NonMaxU64::new(choose_container(agent_idx as u32));
+            }
+        }
+    }
+}
+
+/// Marks occupational containers as schools when most of the agents assigned to them are under 18,
+/// as the synthetic environments don't distinguish between workplaces and schools
+fn classify_schools<M>(ages: &[u8], occupational_container: &[Option<NonMaxU64>], containers: &mut Containers<M>)
+    where M: MixingStrategy
+{
+    let mut counts: HashMap<u64, (u32, u32)> = HashMap::new();
+    for (&age, container_idx) in ages.iter().zip(occupational_container) {
+        if let Some(container_idx) = container_idx {
+            let (num_under_18, total) = counts.entry(container_idx.get()).or_default();
+            if age < 18 { *num_under_18 += 1; }
+            *total += 1;
+        }
+    }
+
+    for (container_idx, (num_under_18, total)) in counts {
+        if num_under_18 * 2 > total {
+            containers.set_container_type(container_idx, ContainerType::School);
+        }
+    }
+}
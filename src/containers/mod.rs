@@ -1,33 +1,70 @@
-use rand::rngs::ThreadRng;
+use std::error::Error;
+use std::fmt;
 
-use crate::agents::Agents;
-use crate::disease::{DiseaseStatus, MixingStrategy, Uniform};
+use serde::{Deserialize, Serialize};
+
+use crate::agents::{AgentLocations, Agents, Location};
+use crate::disease::{DiseaseStatus, InfectionSource, Masks, MaskUsage, MixingStrategy, Transmission, Uniform};
 use crate::flatbuffer::Vec2;
+use crate::shared::{RngStream, seeded_counter_rng};
 use crate::shared::types::TimeStep;
 
+/// The kind of place a container represents
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerType {
+    Household,
+    Workplace,
+    /// An occupational container where most of those assigned to it are under 18
+    School,
+    /// Where hospitalised agents are treated, also the occupational container of healthcare workers
+    Hospital,
+}
+
 /// A Spatial Area where agents spend time and mix
 #[derive(Clone)]
 pub struct Container<M: MixingStrategy> {
     pub pos: Vec2,
+    pub container_type: ContainerType,
     pub inhabitants: Vec<u32>,
     pub mixing_strategy: M,
+    /// The masks required within the container, if any
+    pub mask_usage: Option<MaskUsage>,
     last_update: TimeStep,
 }
 
 impl<M: MixingStrategy> Container<M> {
-    fn update(&mut self, agents: &mut Agents, time_step: TimeStep) {
+    fn update(&mut self, container_idx: u64, agents: &mut Agents, time_step: TimeStep) {
+        let wearing: Option<Vec<bool>> = self.mask_usage.map(|usage| {
+            self.inhabitants.iter().map(|&idx| usage.wears_mask(agents.mask_propensity[idx as usize])).collect()
+        });
+        let masks = self.mask_usage.zip(wearing.as_deref()).map(|(usage, wearing)| Masks { wearing, usage });
+
         let start = DiseaseStatusPointer(agents.disease_statuses.as_mut_ptr());
         let mut mut_refs = self.inhabitants.iter().map(|&idx| {
             // Inspired by (taken from) https://stackoverflow.com/a/56009251/14687716
             // As the reasoning in the post explains, this relies on ensuring indices are unique and retrieving the
             // raw pointer avoids aliasing. A person should only be in one container at a time and the vec doesn't
             // get re-ordered so the indices are always unique
-            // TODO Find a way to *ensure* this stays true in the code
+            // This is checked by `Containers::verify_locations` in debug builds
             unsafe { &mut *start.0.add(idx as usize) }
         }).collect::<Vec<&mut DiseaseStatus>>();
 
         let time_steps_since_update = time_step - self.last_update;
-        self.mixing_strategy.handle_transmission(mut_refs.as_mut_slice(), &mut ThreadRng::default(), time_steps_since_update);
+        // there's an update for every container an agent enters or leaves, so seeding a StdRng for each is too slow
+        let mut rng = seeded_counter_rng(RngStream::Transmission, &[container_idx, time_step as u64]);
+        let infections = self.mixing_strategy.handle_transmission(mut_refs.as_mut_slice(), &mut rng, time_steps_since_update, masks.as_ref());
+        for infection in infections {
+            agents.transmission_log.push(Transmission {
+                time_step,
+                infectee: self.inhabitants[infection.infectee],
+                infector: infection.infector.map(|infector| self.inhabitants[infector]),
+                source: InfectionSource::Container,
+                container_idx: Some(container_idx),
+                container_type: Some(self.container_type),
+                strain: 0,
+            });
+        }
 
         self.last_update = time_step;
     }
@@ -39,6 +76,22 @@ pub struct Containers<M: MixingStrategy> {
     pub(crate) num_households: u32,
 }
 
+/// An agent whose location doesn't match the containers they're an inhabitant of
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationMismatch {
+    pub agent_idx: u32,
+    pub location: Location,
+    pub inhabitant_of: Vec<u64>,
+}
+
+impl fmt::Display for LocationMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "agent {} is at {:?} but is an inhabitant of containers {:?}", self.agent_idx, self.location, self.inhabitant_of)
+    }
+}
+
+impl Error for LocationMismatch {}
+
 struct DiseaseStatusPointer(*mut DiseaseStatus);
 
 unsafe impl Send for DiseaseStatusPointer {}
@@ -74,7 +127,7 @@ impl<M: MixingStrategy> Containers<M> {
     pub fn remove_inhabitant(&mut self, container_idx: u64, agent_idx: u32, time_step: TimeStep, agents: &mut Agents) {
         let container = self.elements.get_mut(container_idx as usize).unwrap();
         if time_step > container.last_update {
-            container.update(agents, time_step);
+            container.update(container_idx, agents, time_step);
         }
         container.inhabitants.swap_remove(
             container.inhabitants.iter().position(|idx| *idx == agent_idx).expect("Couldn't find given agent index in container")
@@ -91,11 +144,82 @@ impl<M: MixingStrategy> Containers<M> {
     pub fn push_inhabitant(&mut self, container_idx: u64, agent_idx: u32, time_step: TimeStep, agents: &mut Agents) {
         let container = self.elements.get_mut(container_idx as usize).unwrap();
         if time_step > container.last_update {
-            container.update(agents, time_step);
+            container.update(container_idx, agents, time_step);
         }
         container.inhabitants.push(agent_idx);
     }
 
+    /// Brings the transmission within every container up to date, to be used before changing
+    /// anything that affects transmission so the change isn't applied retrospectively
+    pub fn update_all(&mut self, time_step: TimeStep, agents: &mut Agents) {
+        for (container_idx, container) in self.elements.iter_mut().enumerate() {
+            if time_step > container.last_update {
+                container.update(container_idx as u64, agents, time_step);
+            }
+        }
+    }
+
+    /// Adds a container after all of the existing ones, returning its index
+    pub fn push_container(&mut self, pos: Vec2, container_type: ContainerType, mixing_strategy: M) -> u64 {
+        self.elements.push(Container {
+            pos,
+            container_type,
+            inhabitants: Vec::new(),
+            mixing_strategy,
+            mask_usage: None,
+            last_update: 0,
+        });
+        self.elements.len() as u64 - 1
+    }
+
+    /// Checks that every agent is an inhabitant of exactly the container they're located in, or of
+    /// none while they're in transit or once they've been removed
+    pub fn verify_locations(&self, locations: &AgentLocations) -> Result<(), LocationMismatch> {
+        let mut inhabitant_of: Vec<Option<u64>> = vec![None; locations.len()];
+        for (container_idx, container) in self.elements.iter().enumerate() {
+            for &agent_idx in &container.inhabitants {
+                if let Some(other_container_idx) = inhabitant_of[agent_idx as usize] {
+                    return Err(LocationMismatch {
+                        agent_idx,
+                        location: locations.get(agent_idx),
+                        inhabitant_of: vec![other_container_idx, container_idx as u64],
+                    });
+                }
+                inhabitant_of[agent_idx as usize] = Some(container_idx as u64);
+            }
+        }
+
+        for (agent_idx, (location, container_idx)) in locations.iter().zip(inhabitant_of).enumerate() {
+            let expected = match location {
+                Location::InContainer(expected) => { Some(*expected) }
+                Location::InTransit(_) | Location::Removed => { None }
+            };
+            if expected != container_idx {
+                return Err(LocationMismatch {
+                    agent_idx: agent_idx as u32,
+                    location: *location,
+                    inhabitant_of: container_idx.into_iter().collect(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) fn set_container_type(&mut self, container_idx: u64, container_type: ContainerType) {
+        self.elements[container_idx as usize].container_type = container_type;
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=&Container<M>> {
+        self.elements.iter()
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> impl Iterator<Item=&mut Container<M>> {
+        self.elements.iter_mut()
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.elements.len()
@@ -108,14 +232,18 @@ impl<M: MixingStrategy> Containers<M> {
 impl Containers<Uniform> {
     // TODO Investigate options to avoid ownership and duplication of mixing strategy, maybe use an enum or callback for mixing_strategy type to avoid needing lifetime params
     pub fn new(household_positions: &[Vec2], workplace_positions: &[Vec2], mixing_strategy: Uniform) -> Self {
-        let containers = household_positions.iter().chain(workplace_positions).map(|pos| {
-            Container {
-                pos: *pos,
-                inhabitants: Vec::new(),
-                mixing_strategy: mixing_strategy.clone(),
-                last_update: 0,
-            }
-        }).collect();
+        let containers = household_positions.iter().map(|pos| (pos, ContainerType::Household))
+            .chain(workplace_positions.iter().map(|pos| (pos, ContainerType::Workplace)))
+            .map(|(pos, container_type)| {
+                Container {
+                    pos: *pos,
+                    container_type,
+                    inhabitants: Vec::new(),
+                    mixing_strategy: mixing_strategy.clone(),
+                    mask_usage: None,
+                    last_update: 0,
+                }
+            }).collect();
 
         Self {
             elements: containers,
@@ -124,3 +252,25 @@ impl Containers<Uniform> {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_locations() {
+        let positions = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0)];
+        let mut containers = Containers::new(&positions, &positions[..1], Uniform { transmission_chance: 0.0 });
+        let mut locations = AgentLocations::new(&[0, 1]);
+        containers.push_inhabitant_no_update(0, 0);
+        containers.push_inhabitant_no_update(1, 1);
+        assert!(containers.verify_locations(&locations).is_ok());
+
+        containers.push_inhabitant_no_update(2, 1);
+        assert_eq!(containers.verify_locations(&locations).unwrap_err().inhabitant_of, vec![1, 2]);
+
+        containers.elements[1].inhabitants.clear();
+        assert_eq!(containers.verify_locations(&locations).unwrap_err().agent_idx, 1);
+        locations.set(1, Location::InContainer(2), 0);
+        assert!(containers.verify_locations(&locations).is_ok());
+    }
+}
@@ -0,0 +1,321 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::Agents;
+use crate::containers::Containers;
+use crate::disease::MixingStrategy;
+use crate::routing::{KdTree, SpatialIndex};
+use crate::shared::{get_time_steps_per_day, RngStream, seeded_rng};
+use crate::shared::types::TimeStep;
+use crate::Vec2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HospitalisationParams {
+    /// The chance an infection leads to the agent being admitted to hospital
+    pub hospitalisation_chance: f32,
+    /// The chance a hospitalised agent needs an ICU bed rather than a general one
+    pub icu_chance: f32,
+    /// Days from infection to admission
+    pub admission_days: f32,
+    pub stay_days: f32,
+    /// The chance an agent in a general bed dies at the end of their stay rather than being discharged
+    pub fatality_chance: f32,
+    pub icu_fatality_chance: f32,
+    /// The chance an agent dies when there's no bed of the type they need in any hospital
+    pub unmet_need_fatality_chance: f32,
+    /// Scales the chance of transmission within a hospital relative to elsewhere, e.g. to reflect PPE
+    pub hospital_transmission_scale: f32,
+    /// The share of agents with an occupation who work in a hospital instead, exposing them to
+    /// hospitalised agents
+    pub healthcare_worker_share: f32,
+    /// The beds of the single hospital used for models without any hospitals
+    pub default_general_beds: u32,
+    pub default_icu_beds: u32,
+}
+
+impl Default for HospitalisationParams {
+    fn default() -> Self {
+        HospitalisationParams {
+            hospitalisation_chance: 0.03,
+            icu_chance: 0.2,
+            admission_days: 7.0,
+            stay_days: 8.0,
+            fatality_chance: 0.15,
+            icu_fatality_chance: 0.4,
+            unmet_need_fatality_chance: 0.6,
+            hospital_transmission_scale: 0.5,
+            healthcare_worker_share: 0.02,
+            default_general_beds: 500,
+            default_icu_beds: 50,
+        }
+    }
+}
+
+/// The kind of bed a hospitalised agent is in
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Bed {
+    General,
+    Icu,
+    /// Admitted without a bed, as every hospital was full
+    Unavailable,
+}
+
+/// A hospital container and how many of its beds are in use
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hospital {
+    pub container_idx: u64,
+    pub general_beds: u32,
+    pub icu_beds: u32,
+    pub general_occupied: u32,
+    pub icu_occupied: u32,
+    /// Agents admitted to the hospital without a bed of the type they needed
+    pub over_capacity: u32,
+}
+
+impl Hospital {
+    pub fn new(container_idx: u64, general_beds: u32, icu_beds: u32) -> Self {
+        Hospital { container_idx, general_beds, icu_beds, general_occupied: 0, icu_occupied: 0, over_capacity: 0 }
+    }
+
+    #[inline]
+    fn has_free(&self, bed: Bed) -> bool {
+        match bed {
+            Bed::General => { self.general_occupied < self.general_beds }
+            Bed::Icu => { self.icu_occupied < self.icu_beds }
+            Bed::Unavailable => { true }
+        }
+    }
+
+    #[inline]
+    fn occupancy_mut(&mut self, bed: Bed) -> &mut u32 {
+        match bed {
+            Bed::General => { &mut self.general_occupied }
+            Bed::Icu => { &mut self.icu_occupied }
+            Bed::Unavailable => { &mut self.over_capacity }
+        }
+    }
+}
+
+/// An agent's stay in hospital
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Stay {
+    hospital: usize,
+    bed: Bed,
+    discharge_time_step: TimeStep,
+}
+
+/// The agents to move in and out of hospital at the start of a day
+#[derive(Default)]
+pub struct HospitalUpdates {
+    /// The agents admitted and the hospital container they've been admitted to
+    pub admissions: Vec<(u32, u64)>,
+    pub discharges: Vec<u32>,
+    pub deaths: Vec<u32>,
+}
+
+/// # Hospitalisation
+/// The hospitals, how many of their beds are in use, and which agents are in them until when.
+///
+/// Hospitalised agents are admitted to the nearest hospital to their household with a free bed of
+/// the type they need, or to the nearest hospital without a bed if they're all full, where they're
+/// more likely to die.
+///
+/// Whether an agent would be hospitalised, whether they'd need intensive care, and their chance of
+/// dying are drawn once at the start, as agents can't be reinfected.
+#[derive(Clone)]
+pub struct Hospitalisation {
+    params: HospitalisationParams,
+    hospitals: Vec<Hospital>,
+    hospital_index: KdTree<usize>,
+    hospitalised_if_infected: Vec<bool>,
+    needs_icu: Vec<bool>,
+    fatality_draws: Vec<f32>,
+    has_been_admitted: Vec<bool>,
+    stays: Vec<Option<Stay>>,
+    num_deaths: usize,
+}
+
+impl Hospitalisation {
+    pub fn new(params: HospitalisationParams, hospitals: Vec<(Vec2, Hospital)>, num_agents: u32) -> Self {
+        assert!(!hospitals.is_empty(), "Hospitalisation needs at least one hospital");
+        let mut rng = seeded_rng(RngStream::Hospitalisation, &[]);
+        let hospital_index = KdTree::new(hospitals.iter().enumerate().map(|(idx, (pos, _))| (*pos, idx)).collect());
+        Hospitalisation {
+            hospitalised_if_infected: (0..num_agents).map(|_| rng.gen::<f32>() < params.hospitalisation_chance).collect(),
+            needs_icu: (0..num_agents).map(|_| rng.gen::<f32>() < params.icu_chance).collect(),
+            fatality_draws: (0..num_agents).map(|_| rng.gen::<f32>()).collect(),
+            has_been_admitted: vec![false; num_agents as usize],
+            stays: vec![None; num_agents as usize],
+            num_deaths: 0,
+            hospitals: hospitals.into_iter().map(|(_, hospital)| hospital).collect(),
+            hospital_index,
+            params,
+        }
+    }
+
+    #[inline]
+    pub fn params(&self) -> &HospitalisationParams {
+        &self.params
+    }
+
+    #[inline]
+    pub fn hospitals(&self) -> &[Hospital] {
+        &self.hospitals
+    }
+
+    #[inline]
+    pub fn is_hospitalised(&self, agent_idx: u32) -> bool {
+        self.stays[agent_idx as usize].is_some()
+    }
+
+    #[inline]
+    pub fn num_hospitalised(&self) -> usize {
+        self.stays.iter().filter(|stay| stay.is_some()).count()
+    }
+
+    /// The number of general and ICU beds across every hospital
+    #[inline]
+    pub fn num_beds(&self) -> usize {
+        self.hospitals.iter().map(|hospital| (hospital.general_beds + hospital.icu_beds) as usize).sum()
+    }
+
+    /// The number of agents who've been admitted to hospital so far
+    #[inline]
+    pub fn num_admissions(&self) -> usize {
+        self.has_been_admitted.iter().filter(|&&admitted| admitted).count()
+    }
+
+    #[inline]
+    pub fn num_deaths(&self) -> usize {
+        self.num_deaths
+    }
+
+    /// Finds the nearest hospital with a free bed of the type needed, falling back to the nearest
+    /// hospital without a bed
+    fn assign_bed(&self, pos: Vec2, needs_icu: bool) -> (usize, Bed) {
+        let bed = if needs_icu { Bed::Icu } else { Bed::General };
+        let nearest = self.hospital_index.nearest(pos, self.hospitals.len(), f32::INFINITY);
+        nearest.iter()
+            .find(|neighbour| self.hospitals[neighbour.item].has_free(bed))
+            .map_or((nearest[0].item, Bed::Unavailable), |neighbour| (neighbour.item, bed))
+    }
+
+    #[inline]
+    fn fatality_chance(&self, bed: Bed) -> f32 {
+        match bed {
+            Bed::General => { self.params.fatality_chance }
+            Bed::Icu => { self.params.icu_fatality_chance }
+            Bed::Unavailable => { self.params.unmet_need_fatality_chance }
+        }
+    }
+
+    /// Admits the agents whose infections have become severe and discharges (or records the deaths
+    /// of) those whose stay has ended.
+    ///
+    /// The agents' disease statuses should be up to date as of the given time step.
+    pub fn update_day<M>(&mut self, time_step: TimeStep, agents: &Agents, containers: &Containers<M>) -> HospitalUpdates
+        where M: MixingStrategy
+    {
+        let admit_after = (self.params.admission_days * get_time_steps_per_day() as f32) as TimeStep;
+        let stay_time_steps = (self.params.stay_days * get_time_steps_per_day() as f32) as TimeStep;
+        let mut updates = HospitalUpdates::default();
+
+        // discharge first so the beds are free for the day's admissions
+        for agent_idx in 0..agents.num_agents {
+            let idx = agent_idx as usize;
+            if let Some(stay) = self.stays[idx].filter(|stay| stay.discharge_time_step <= time_step) {
+                self.stays[idx] = None;
+                *self.hospitals[stay.hospital].occupancy_mut(stay.bed) -= 1;
+                if self.fatality_draws[idx] < self.fatality_chance(stay.bed) {
+                    self.num_deaths += 1;
+                    updates.deaths.push(agent_idx);
+                } else {
+                    updates.discharges.push(agent_idx);
+                }
+            }
+        }
+
+        for agent_idx in 0..agents.num_agents {
+            let idx = agent_idx as usize;
+            let status = &agents.disease_statuses[idx];
+            if self.has_been_admitted[idx] || !self.hospitalised_if_infected[idx]
+                || !status.is_infected() || status.infected_for() < admit_after {
+                continue;
+            }
+            let household_pos = containers.get(agents.household_container[idx]).unwrap().pos;
+            let (hospital, bed) = self.assign_bed(household_pos, self.needs_icu[idx]);
+            *self.hospitals[hospital].occupancy_mut(bed) += 1;
+            self.has_been_admitted[idx] = true;
+            self.stays[idx] = Some(Stay { hospital, bed, discharge_time_step: time_step + stay_time_steps });
+            updates.admissions.push((agent_idx, self.hospitals[hospital].container_idx));
+        }
+        updates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disease::Uniform;
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+
+    use super::*;
+
+    #[test]
+    fn test_assign_nearest_bed_with_capacity() {
+        let hospitals = vec![
+            (Vec2::new(0.0, 0.0), Hospital::new(10, 1, 0)),
+            (Vec2::new(10.0, 0.0), Hospital::new(11, 1, 1)),
+        ];
+        let mut hospitalisation = Hospitalisation::new(HospitalisationParams::default(), hospitals, 0);
+
+        assert_eq!(hospitalisation.assign_bed(Vec2::new(1.0, 0.0), false), (0, Bed::General));
+        assert_eq!(hospitalisation.assign_bed(Vec2::new(1.0, 0.0), true), (1, Bed::Icu));
+
+        hospitalisation.hospitals[0].general_occupied = 1;
+        assert_eq!(hospitalisation.assign_bed(Vec2::new(1.0, 0.0), false), (1, Bed::General));
+        hospitalisation.hospitals[1].general_occupied = 1;
+        assert_eq!(hospitalisation.assign_bed(Vec2::new(1.0, 0.0), false), (0, Bed::Unavailable));
+    }
+
+    #[test]
+    fn test_admission_discharge_and_death() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let tspd = get_time_steps_per_day();
+        let containers = Containers::new(&[Vec2::new(0.0, 0.0)], &[], Uniform { transmission_chance: 0.0 });
+        let mut agents = Agents::with_households(vec![70; 2], vec![0, 0]);
+        agents.disease_statuses[0].infect();
+        let params = HospitalisationParams {
+            hospitalisation_chance: 1.0,
+            icu_chance: 0.0,
+            admission_days: 2.0,
+            stay_days: 3.0,
+            ..HospitalisationParams::default()
+        };
+
+        for (fatality_chance, dies) in [(0.0, false), (1.0, true)] {
+            let mut agents = agents.clone();
+            let params = HospitalisationParams { fatality_chance, ..params.clone() };
+            let mut hospitalisation = Hospitalisation::new(params, vec![(Vec2::new(0.0, 0.0), Hospital::new(1, 1, 0))], 2);
+
+            // admitted once their infection's lasted long enough, into a general bed
+            agents.disease_statuses[0].progress_infection(tspd);
+            assert!(hospitalisation.update_day(tspd, &agents, &containers).admissions.is_empty());
+            agents.disease_statuses[0].progress_infection(tspd);
+            assert_eq!(hospitalisation.update_day(2 * tspd, &agents, &containers).admissions, vec![(0, 1)]);
+            assert!(hospitalisation.is_hospitalised(0) && !hospitalisation.is_hospitalised(1));
+            assert_eq!(hospitalisation.hospitals()[0].general_occupied, 1);
+
+            // and leave, one way or the other, once their stay is over, freeing the bed
+            assert!(hospitalisation.update_day(4 * tspd, &agents, &containers).discharges.is_empty());
+            let updates = hospitalisation.update_day(5 * tspd, &agents, &containers);
+            assert_eq!((updates.discharges.len(), updates.deaths.len()), if dies { (0, 1) } else { (1, 0) });
+            assert_eq!(hospitalisation.num_deaths(), dies as usize);
+            assert!(!hospitalisation.is_hospitalised(0));
+            assert_eq!(hospitalisation.hospitals()[0].general_occupied, 0);
+            // agents are only admitted once
+            assert!(hospitalisation.update_day(6 * tspd, &agents, &containers).admissions.is_empty());
+            assert_eq!(hospitalisation.num_admissions(), 1);
+        }
+    }
+}
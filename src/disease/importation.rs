@@ -0,0 +1,224 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::{Bounds, TransitGraph, Vec2};
+use crate::agents::Agents;
+use crate::containers::Containers;
+use crate::disease::{MixingStrategy, State};
+use crate::routing::{KdTree, SpatialIndex};
+
+/// The expected number of infections imported each day from the given day onwards
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportationRate {
+    pub start_day: u32,
+    pub daily_infections: f32,
+}
+
+/// Which agents imported infections are given to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ImportationTarget {
+    #[default]
+    Anyone,
+    /// Agents whose occupational container is within `distance` of the edge of the modelled region
+    BoundaryCommuters { distance: f32 },
+    /// Agents whose household or occupational container is within `radius` of one of the
+    /// `num_hubs` transit stops with the most connections
+    NearTransitHubs { num_hubs: usize, radius: f32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportationParams {
+    /// The rates over time, ordered by `start_day`, no infections being imported before the first
+    pub rates: Vec<ImportationRate>,
+    #[serde(default)]
+    pub target: ImportationTarget,
+}
+
+impl ImportationParams {
+    /// A constant rate of imported infections from the start, given to anyone
+    pub fn constant(daily_infections: f32) -> Self {
+        ImportationParams {
+            rates: vec![ImportationRate { start_day: 0, daily_infections }],
+            target: ImportationTarget::Anyone,
+        }
+    }
+
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+}
+
+/// # Importation
+/// Infections from outside of the modelled region, e.g. from agents travelling, so that small
+/// regions don't simply burn out after the initial seeding.
+#[derive(Clone)]
+pub struct Importation {
+    params: ImportationParams,
+    /// The agents imported infections can be given to, `None` for anyone
+    eligible: Option<Vec<u32>>,
+    num_imported: usize,
+}
+
+impl Importation {
+    pub fn new<M>(params: ImportationParams, agents: &Agents, containers: &Containers<M>, bounds: &Bounds,
+                  transit_graph: &TransitGraph) -> Self
+        where M: MixingStrategy
+    {
+        let eligible = match params.target {
+            ImportationTarget::Anyone => { None }
+            ImportationTarget::BoundaryCommuters { distance } => {
+                Some(filter_agents(agents, containers, |_, occupational_pos| {
+                    occupational_pos.is_some_and(|pos| distance_to_boundary(pos, bounds) <= distance)
+                }))
+            }
+            ImportationTarget::NearTransitHubs { num_hubs, radius } => {
+                let hubs = KdTree::new(transit_hubs(transit_graph, num_hubs).into_iter()
+                    .map(|node_idx| (*transit_graph.nodes()[node_idx].pos(), node_idx))
+                    .collect());
+                let near_hub = |pos: Vec2| !hubs.within_radius(pos, radius).is_empty();
+                Some(filter_agents(agents, containers, |household_pos, occupational_pos| {
+                    near_hub(household_pos) || occupational_pos.is_some_and(near_hub)
+                }))
+            }
+        };
+        Importation { params, eligible, num_imported: 0 }
+    }
+
+    #[inline]
+    pub fn params(&self) -> &ImportationParams {
+        &self.params
+    }
+
+    #[inline]
+    pub fn num_imported(&self) -> usize {
+        self.num_imported
+    }
+
+    /// The expected number of infections imported on the given day
+    #[inline]
+    pub fn rate_on(&self, day: u32) -> f32 {
+        self.params.rates.iter().rev()
+            .find(|rate| rate.start_day <= day)
+            .map_or(0.0, |rate| rate.daily_infections)
+    }
+
+    /// Chooses the susceptible agents to be infected on the given day
+    pub fn update_day<R>(&mut self, day: u32, agents: &Agents, rng: &mut R) -> Vec<u32>
+        where R: Rng + ?Sized
+    {
+        let num_infections = sample_poisson(self.rate_on(day), rng);
+        let mut infected = Vec::with_capacity(num_infections as usize);
+        // give up on an infection after a few draws of agents who aren't susceptible, so a mostly
+        // infected population doesn't loop forever
+        for _ in 0..num_infections * 10 {
+            if infected.len() as u32 == num_infections {
+                break;
+            }
+            let agent_idx = match &self.eligible {
+                Some(eligible) => { eligible.choose(rng).copied() }
+                None => { Some(rng.gen_range(0..agents.num_agents)) }
+            };
+            if let Some(agent_idx) = agent_idx {
+                if agents.disease_statuses[agent_idx as usize].state == State::Susceptible && !infected.contains(&agent_idx) {
+                    infected.push(agent_idx);
+                }
+            }
+        }
+        self.num_imported += infected.len();
+        infected
+    }
+}
+
+fn filter_agents<M, F>(agents: &Agents, containers: &Containers<M>, mut filter: F) -> Vec<u32>
+    where M: MixingStrategy, F: FnMut(Vec2, Option<Vec2>) -> bool
+{
+    (0..agents.num_agents).filter(|&agent_idx| {
+        let household_pos = containers.get(agents.household_container[agent_idx as usize]).unwrap().pos;
+        let occupational_pos = agents.occupational_container[agent_idx as usize]
+            .map(|container_idx| containers.get(container_idx.get()).unwrap().pos);
+        filter(household_pos, occupational_pos)
+    }).collect()
+}
+
+#[inline]
+fn distance_to_boundary(pos: Vec2, bounds: &Bounds) -> f32 {
+    (pos.x() - bounds.min().x()).min(bounds.max().x() - pos.x())
+        .min(pos.y() - bounds.min().y()).min(bounds.max().y() - pos.y())
+}
+
+/// The indices of the transit nodes with the most edges
+fn transit_hubs(transit_graph: &TransitGraph, num_hubs: usize) -> Vec<usize> {
+    let mut degrees = vec![0u32; transit_graph.nodes().len()];
+    for edge in transit_graph.edges().iter() {
+        degrees[edge.start_node_index() as usize] += 1;
+        degrees[edge.end_node_index() as usize] += 1;
+    }
+    let mut node_indices: Vec<usize> = (0..degrees.len()).collect();
+    node_indices.sort_unstable_by(|&a, &b| degrees[b].cmp(&degrees[a]));
+    node_indices.truncate(num_hubs);
+    node_indices
+}
+
+/// Samples a Poisson distribution by Knuth's method, falling back to a normal approximation for
+/// larger means where it would be slow
+fn sample_poisson<R>(mean: f32, rng: &mut R) -> u32
+    where R: Rng + ?Sized
+{
+    if mean <= 0.0 {
+        return 0;
+    }
+    if mean > 30.0 {
+        // Box-Muller transform
+        let (u1, u2) = (rng.gen::<f32>().max(f32::MIN_POSITIVE), rng.gen::<f32>());
+        let standard_normal = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos();
+        return (mean + mean.sqrt() * standard_normal).round().max(0.0) as u32;
+    }
+    let limit = (-mean).exp();
+    let mut product = rng.gen::<f32>();
+    let mut count = 0;
+    while product > limit {
+        product *= rng.gen::<f32>();
+        count += 1;
+    }
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams, RngStream, seeded_rng};
+
+    use super::*;
+
+    #[test]
+    fn test_sample_poisson_mean() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let mut rng = seeded_rng(RngStream::Importation, &[]);
+        for &mean in [0.5, 4.0, 100.0].iter() {
+            let num_samples = 20_000;
+            let total: u32 = (0..num_samples).map(|_| sample_poisson(mean, &mut rng)).sum();
+            let sample_mean = total as f32 / num_samples as f32;
+            assert!((sample_mean - mean).abs() < mean * 0.05 + 0.05, "mean {} sampled as {}", mean, sample_mean);
+        }
+        assert_eq!(sample_poisson(0.0, &mut rng), 0);
+    }
+
+    #[test]
+    fn test_parse_importation() {
+        let params: ImportationParams = serde_json::from_str(r#"{
+            "rates": [{"start_day": 0, "daily_infections": 0.5}, {"start_day": 30, "daily_infections": 2.0}],
+            "target": {"type": "near_transit_hubs", "num_hubs": 5, "radius": 1000.0}
+        }"#).unwrap();
+        assert_eq!(params.target, ImportationTarget::NearTransitHubs { num_hubs: 5, radius: 1000.0 });
+
+        let importation = Importation { params, eligible: None, num_imported: 0 };
+        assert_eq!(importation.rate_on(0), 0.5);
+        assert_eq!(importation.rate_on(29), 0.5);
+        assert_eq!(importation.rate_on(30), 2.0);
+    }
+}
@@ -1,13 +1,73 @@
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
 use crate::disease::{DiseaseStatus, State};
 use crate::shared::types::TimeStep;
 
 pub trait MixingStrategy<T: Send + Sync = Self>: Send + Sync {
-    fn handle_transmission<R>(&self, statuses: &mut [&mut DiseaseStatus], rng: &mut R, for_time_steps: TimeStep)
+    /// Progresses the infections of the agents in a container and infects others, returning the
+    /// new infections
+    fn handle_transmission<R>(&self, statuses: &mut [&mut DiseaseStatus], rng: &mut R, for_time_steps: TimeStep,
+                              masks: Option<&Masks>) -> Vec<Infection>
         where R: Rng + ?Sized;
 }
 
+/// A new infection within a container, the agents being given by their position in the statuses
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Infection {
+    pub infectee: usize,
+    /// `None` if the strategy only attributes infections to the container
+    pub infector: Option<usize>,
+}
+
+/// How many agents wear masks within a container and how effective they are
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct MaskUsage {
+    /// The share of agents who wear a mask, being those with the highest mask propensities
+    pub compliance: f32,
+    /// The reduction in the chance of an infectious agent wearing a mask infecting others
+    pub source_control: f32,
+    /// The reduction in the chance of a susceptible agent wearing a mask being infected
+    pub wearer_protection: f32,
+}
+
+impl MaskUsage {
+    #[inline]
+    pub fn wears_mask(&self, mask_propensity: f32) -> bool {
+        mask_propensity >= 1.0 - self.compliance
+    }
+
+    /// Combines the usage required by two policies, taking the more stringent of each
+    #[inline]
+    pub fn max(self, other: MaskUsage) -> MaskUsage {
+        MaskUsage {
+            compliance: self.compliance.max(other.compliance),
+            source_control: self.source_control.max(other.source_control),
+            wearer_protection: self.wearer_protection.max(other.wearer_protection),
+        }
+    }
+}
+
+/// Which of the agents in a container are wearing masks, in the same order as their statuses
+pub struct Masks<'a> {
+    pub wearing: &'a [bool],
+    pub usage: MaskUsage,
+}
+
+impl<'a> Masks<'a> {
+    /// Scales how infectious the agent is to others
+    #[inline]
+    pub fn infectiousness(&self, idx: usize) -> f32 {
+        if self.wearing[idx] { 1.0 - self.usage.source_control } else { 1.0 }
+    }
+
+    /// Scales how likely the agent is to be infected
+    #[inline]
+    pub fn susceptibility(&self, idx: usize) -> f32 {
+        if self.wearing[idx] { 1.0 - self.usage.wearer_protection } else { 1.0 }
+    }
+}
+
 #[derive(Clone)]
 pub struct Uniform {
     // Chance an infected person might infect someone else in their container per time step
@@ -20,31 +80,120 @@ pub struct Uniform {
 
 /// Super basic Mixing strategy with a flat exposure rate based on how many infected people are
 /// in the container, doesn't take an individual's susceptibility, length of infection, distances,
-/// etc. into consideration
+/// etc. into consideration, other than whether they're wearing a mask
 impl MixingStrategy for Uniform {
     #[inline]
-    fn handle_transmission<R>(&self, statuses: &mut [&mut DiseaseStatus], rng: &mut R, for_time_steps: TimeStep)
+    fn handle_transmission<R>(&self, statuses: &mut [&mut DiseaseStatus], rng: &mut R, for_time_steps: TimeStep,
+                              masks: Option<&Masks>) -> Vec<Infection>
         where R: Rng + ?Sized
     {
-        let mut num_infected = 0;
+        // the agents who were infectious over the interval and how infectious they were, taken
+        // before their infections progress as some may recover and others only now become infectious
+        let mut infectious = Vec::new();
 
         // TODO revisit keeping track of susceptible in this loop, speed was tested for only households which are smaller than workplaces
-        for status in statuses.iter_mut() {
+        for (idx, status) in statuses.iter_mut().enumerate() {
             if status.state == State::Exposed || status.state == State::Infectious {
                 if status.state == State::Infectious {
-                    num_infected += 1;
+                    infectious.push((idx, masks.map_or(1.0, |masks| masks.infectiousness(idx))));
                 }
 
                 status.progress_infection(for_time_steps);
             }
         }
+        // the number of infectious agents, discounted by those wearing masks
+        let infectious_load: f32 = infectious.iter().map(|&(_, infectiousness)| infectiousness).sum();
 
         // TODO handle recoveries and new infections
-        let chance = self.transmission_chance * (num_infected as f32) * (for_time_steps as f32);
-        for agent_status in statuses.iter_mut() {
-            if agent_status.state == State::Susceptible && rng.gen::<f32>() < chance {
-                agent_status.infect()
+        let chance = self.transmission_chance * infectious_load * (for_time_steps as f32);
+        let mut infectees = Vec::new();
+        for (idx, agent_status) in statuses.iter_mut().enumerate() {
+            let agent_chance = chance * masks.map_or(1.0, |masks| masks.susceptibility(idx));
+            if agent_status.state == State::Susceptible && rng.gen::<f32>() < agent_chance {
+                agent_status.infect();
+                infectees.push(idx);
             };
         };
+
+        infectees.into_iter().map(|infectee| {
+            Infection { infectee, infector: choose_infector(&infectious, infectious_load, rng) }
+        }).collect()
+    }
+}
+
+/// Attributes an infection to one of the agents who were infectious, given with how infectious they
+/// were, in proportion to how infectious they were
+fn choose_infector<R>(infectious: &[(usize, f32)], infectious_load: f32, rng: &mut R) -> Option<usize>
+    where R: Rng + ?Sized
+{
+    let mut remaining = rng.gen::<f32>() * infectious_load;
+    for &(idx, infectiousness) in infectious {
+        remaining -= infectiousness;
+        if remaining < 0.0 {
+            return Some(idx);
+        }
+    }
+    // falls back to the last infectious agent when rounding leaves some of the draw remaining
+    infectious.last().map(|&(idx, _)| idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams, get_time_steps_per_day, RngStream, seeded_rng};
+
+    use super::*;
+
+    #[test]
+    fn test_mask_usage() {
+        let usage = MaskUsage { compliance: 0.7, source_control: 0.5, wearer_protection: 0.2 };
+        assert!(usage.wears_mask(0.9));
+        assert!(!usage.wears_mask(0.1));
+
+        let masks = Masks { wearing: &[true, false], usage };
+        assert_eq!(masks.infectiousness(0), 0.5);
+        assert_eq!(masks.susceptibility(0), 0.8);
+        assert_eq!(masks.infectiousness(1), 1.0);
+        assert_eq!(masks.susceptibility(1), 1.0);
+
+        let stricter = usage.max(MaskUsage { compliance: 0.9, source_control: 0.3, wearer_protection: 0.4 });
+        assert_eq!(stricter, MaskUsage { compliance: 0.9, source_control: 0.5, wearer_protection: 0.4 });
+    }
+
+    #[test]
+    fn test_infections_attributed_to_infectious_agents() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let mut statuses = [DiseaseStatus::susceptible(); 3];
+        statuses[1].state = State::Infectious;
+        let mut refs: Vec<&mut DiseaseStatus> = statuses.iter_mut().collect();
+
+        let infections = Uniform { transmission_chance: 1.0 }
+            .handle_transmission(refs.as_mut_slice(), &mut seeded_rng(RngStream::Transmission, &[]), 1, None);
+        assert_eq!(infections, vec![
+            Infection { infectee: 0, infector: Some(1) },
+            Infection { infectee: 2, infector: Some(1) },
+        ]);
+    }
+
+    #[test]
+    fn test_infections_attributed_to_those_infectious_during_the_interval() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let tspd = get_time_steps_per_day();
+        let mut statuses = [DiseaseStatus::susceptible(); 4];
+        // recovers during the interval
+        statuses[1].infect();
+        statuses[1].progress_infection(12 * tspd);
+        // only becomes infectious during the interval
+        statuses[2].infect();
+        statuses[2].progress_infection(3 * tspd);
+        assert!(statuses[1].state == State::Infectious && statuses[2].state == State::Exposed);
+        let mut refs: Vec<&mut DiseaseStatus> = statuses.iter_mut().collect();
+
+        let infections = Uniform { transmission_chance: 1.0 }
+            .handle_transmission(refs.as_mut_slice(), &mut seeded_rng(RngStream::Transmission, &[]), 2, None);
+        assert!(statuses[1].state == State::Recovered && statuses[2].state == State::Infectious);
+        assert_eq!(infections, vec![
+            Infection { infectee: 0, infector: Some(1) },
+            Infection { infectee: 3, infector: Some(1) },
+        ]);
     }
 }
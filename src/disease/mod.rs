@@ -1,5 +1,13 @@
 mod status;
 mod mixing;
+mod hospitalisation;
+mod importation;
+mod seeding;
+mod transmission;
 
 pub use status::{State, DiseaseStatus, construct_disease_status_array};
-pub use mixing::{Uniform, MixingStrategy};
\ No newline at end of file
+pub use mixing::{Uniform, MixingStrategy, Infection, Masks, MaskUsage};
+pub use hospitalisation::{Bed, Hospital, HospitalisationParams, Hospitalisation, HospitalUpdates};
+pub use importation::{Importation, ImportationParams, ImportationRate, ImportationTarget};
+pub use seeding::{Seed, SeedNumber, SeedTarget, Seeding, SeedingParams};
+pub use transmission::{InfectionSource, InfectionsBySetting, Transmission, TransmissionLog};
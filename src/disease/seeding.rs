@@ -0,0 +1,288 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::Agents;
+use crate::containers::{Containers, ContainerType};
+use crate::disease::{DiseaseStatus, MixingStrategy, State};
+
+/// Which agents a seed can infect
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SeedTarget {
+    #[default]
+    Anyone,
+    /// Agents whose household is within `radius` of the point (`x`, `y`)
+    Area { x: f32, y: f32, radius: f32 },
+    /// Agents living in one of the households, or working in one of the workplaces, by their index
+    /// in the synthetic environment
+    Containers {
+        #[serde(default)]
+        households: Vec<u32>,
+        #[serde(default)]
+        workplaces: Vec<u32>,
+    },
+    /// Agents aged between `min_age` and `max_age` inclusive
+    AgeGroup { min_age: u8, max_age: u8 },
+    /// The agents listed in the `agent_idx` column of a CSV file
+    AgentFile { path: PathBuf },
+}
+
+/// How many of the targeted agents are infected
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SeedNumber {
+    /// Each targeted agent is infected independently with the given chance
+    Chance(f32),
+    /// Exactly this many of the targeted agents, or all of them if there are fewer
+    Exact(u32),
+    All,
+}
+
+fn default_exposed_share() -> f32 {
+    0.4
+}
+
+/// A group of infections introduced on a given day
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Seed {
+    #[serde(default)]
+    pub day: u32,
+    #[serde(default)]
+    pub target: SeedTarget,
+    pub number: SeedNumber,
+    /// The share of seeds who start exposed rather than infectious
+    #[serde(default = "default_exposed_share")]
+    pub exposed_share: f32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SeedingParams {
+    pub seeds: Vec<Seed>,
+}
+
+impl SeedingParams {
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+}
+
+#[derive(Deserialize)]
+struct SeedAgentRecord {
+    agent_idx: u32,
+}
+
+/// # Seeding
+/// The infections introduced at the start of, or later in, the simulation, replacing the default
+/// of infecting each agent at the start with the `seed_infection_chance`.
+#[derive(Clone)]
+pub struct Seeding {
+    seeds: Vec<Seed>,
+    /// The agents each seed can infect, `None` for anyone
+    eligible: Vec<Option<Vec<u32>>>,
+    num_seeded: usize,
+}
+
+impl Seeding {
+    /// Resolves which agents each seed targets, failing if an agent file can't be read, or if a
+    /// seed targets agents, households or workplaces that don't exist
+    pub fn new<M>(params: SeedingParams, agents: &Agents, containers: &Containers<M>) -> Result<Self, Box<dyn Error>>
+        where M: MixingStrategy
+    {
+        let eligible = params.seeds.iter()
+            .map(|seed| eligible_agents(&seed.target, agents, containers))
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()?;
+        Ok(Seeding { seeds: params.seeds, eligible, num_seeded: 0 })
+    }
+
+    #[inline]
+    pub fn seeds(&self) -> &[Seed] {
+        &self.seeds
+    }
+
+    #[inline]
+    pub fn num_seeded(&self) -> usize {
+        self.num_seeded
+    }
+
+    #[inline]
+    pub fn has_seeds_on(&self, day: u32) -> bool {
+        self.seeds.iter().any(|seed| seed.day == day)
+    }
+
+    /// Infects the susceptible agents chosen by the seeds for the given day, returning them
+    pub fn update_day<R>(&mut self, day: u32, agents: &mut Agents, rng: &mut R) -> Vec<u32>
+        where R: Rng + ?Sized
+    {
+        let mut seeded = Vec::new();
+        for (seed, eligible) in self.seeds.iter().zip(self.eligible.iter()) {
+            if seed.day != day {
+                continue;
+            }
+            let susceptible: Vec<u32> = match eligible {
+                Some(eligible) => { eligible.clone() }
+                None => { (0..agents.num_agents).collect() }
+            }.into_iter()
+                .filter(|&agent_idx| agents.disease_statuses[agent_idx as usize].state == State::Susceptible)
+                .collect();
+            let chosen: Vec<u32> = match seed.number {
+                SeedNumber::Chance(chance) => {
+                    susceptible.into_iter().filter(|_| rng.gen::<f32>() < chance).collect()
+                }
+                SeedNumber::Exact(count) => {
+                    susceptible.choose_multiple(rng, count as usize).copied().collect()
+                }
+                SeedNumber::All => { susceptible }
+            };
+            for &agent_idx in chosen.iter() {
+                agents.disease_statuses[agent_idx as usize] = DiseaseStatus::seeded(rng, seed.exposed_share);
+            }
+            seeded.extend(chosen);
+        }
+        self.num_seeded += seeded.len();
+        seeded
+    }
+}
+
+fn eligible_agents<M>(target: &SeedTarget, agents: &Agents, containers: &Containers<M>) -> Result<Option<Vec<u32>>, Box<dyn Error>>
+    where M: MixingStrategy
+{
+    let eligible = match target {
+        SeedTarget::Anyone => { return Ok(None); }
+        SeedTarget::Area { x, y, radius } => {
+            (0..agents.num_agents).filter(|&agent_idx| {
+                let pos = containers.get(agents.household_container[agent_idx as usize]).unwrap().pos;
+                (pos.x() - x).powi(2) + (pos.y() - y).powi(2) <= radius.powi(2)
+            }).collect()
+        }
+        SeedTarget::Containers { households, workplaces } => {
+            if let Some(household) = households.iter().find(|&&idx| idx >= containers.num_households) {
+                return Err(format!("a seed targets household {} but there are only {} households", household, containers.num_households).into());
+            }
+            // hospitals are added after the workplaces, so an index past the last workplace can still be a container
+            let is_workplace = |idx: u32| containers.get_workplace(idx)
+                .is_some_and(|container| matches!(container.container_type, ContainerType::Workplace | ContainerType::School));
+            if let Some(workplace) = workplaces.iter().find(|&&idx| !is_workplace(idx)) {
+                return Err(format!("a seed targets workplace {} which isn't in the model", workplace).into());
+            }
+            let household_indices: Vec<u64> = households.iter().map(|&idx| containers.get_household_idx(idx)).collect();
+            let workplace_indices: Vec<u64> = workplaces.iter().map(|&idx| containers.get_workplace_idx(idx)).collect();
+            (0..agents.num_agents).filter(|&agent_idx| {
+                household_indices.contains(&agents.household_container[agent_idx as usize])
+                    || agents.occupational_container[agent_idx as usize]
+                    .is_some_and(|container_idx| workplace_indices.contains(&container_idx.get()))
+            }).collect()
+        }
+        SeedTarget::AgeGroup { min_age, max_age } => {
+            (0..agents.num_agents)
+                .filter(|&agent_idx| (*min_age..=*max_age).contains(&agents.ages[agent_idx as usize]))
+                .collect()
+        }
+        SeedTarget::AgentFile { path } => {
+            let mut agent_indices = Vec::new();
+            for record in csv::Reader::from_path(path)?.deserialize() {
+                let record: SeedAgentRecord = record?;
+                if record.agent_idx >= agents.num_agents {
+                    return Err(format!("{} lists agent {} but there are only {} agents",
+                                       path.display(), record.agent_idx, agents.num_agents).into());
+                }
+                agent_indices.push(record.agent_idx);
+            }
+            agent_indices
+        }
+    };
+    Ok(Some(eligible))
+}
+
+#[cfg(test)]
+mod tests {
+    use nonmax::NonMaxU64;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    use crate::disease::Uniform;
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+    use crate::Vec2;
+
+    use super::*;
+
+    fn seeding(target: SeedTarget, number: SeedNumber, agents: &Agents, containers: &Containers<Uniform>) -> Seeding {
+        Seeding::new(SeedingParams { seeds: vec![Seed { day: 2, target, number, exposed_share: 1.0 }] }, agents, containers).unwrap()
+    }
+
+    #[test]
+    fn test_parse_seeding() {
+        let params: SeedingParams = serde_json::from_str(r#"{
+            "seeds": [
+                {"number": {"exact": 10}},
+                {"day": 14, "target": {"type": "age_group", "min_age": 18, "max_age": 30}, "number": {"chance": 0.01}},
+                {"target": {"type": "containers", "workplaces": [3, 7]}, "number": "all", "exposed_share": 1.0}
+            ]
+        }"#).unwrap();
+
+        assert_eq!(params.seeds[0].day, 0);
+        assert_eq!(params.seeds[0].target, SeedTarget::Anyone);
+        assert_eq!(params.seeds[0].number, SeedNumber::Exact(10));
+        assert_eq!(params.seeds[0].exposed_share, 0.4);
+        assert_eq!(params.seeds[1].target, SeedTarget::AgeGroup { min_age: 18, max_age: 30 });
+        assert_eq!(params.seeds[2].target, SeedTarget::Containers { households: Vec::new(), workplaces: vec![3, 7] });
+        assert_eq!(params.seeds[2].number, SeedNumber::All);
+    }
+
+    #[test]
+    fn test_eligible_agents() {
+        let containers = Containers::new(&[Vec2::new(0.0, 0.0), Vec2::new(50.0, 0.0)], &[Vec2::new(0.0, 50.0)],
+                                         Uniform { transmission_chance: 0.0 });
+        let mut agents = Agents::with_households(vec![10, 25, 40, 70], vec![0, 0, 1, 1]);
+        agents.occupational_container[2] = NonMaxU64::new(containers.get_workplace_idx(0));
+
+        let area = SeedTarget::Area { x: 45.0, y: 0.0, radius: 10.0 };
+        assert_eq!(eligible_agents(&area, &agents, &containers).unwrap(), Some(vec![2, 3]));
+        let age_group = SeedTarget::AgeGroup { min_age: 25, max_age: 40 };
+        assert_eq!(eligible_agents(&age_group, &agents, &containers).unwrap(), Some(vec![1, 2]));
+        let households_and_workplaces = SeedTarget::Containers { households: vec![0], workplaces: vec![0] };
+        assert_eq!(eligible_agents(&households_and_workplaces, &agents, &containers).unwrap(), Some(vec![0, 1, 2]));
+        assert_eq!(eligible_agents(&SeedTarget::Anyone, &agents, &containers).unwrap(), None);
+
+        let missing_household = SeedTarget::Containers { households: vec![2], workplaces: Vec::new() };
+        assert!(eligible_agents(&missing_household, &agents, &containers).is_err());
+        let missing_workplace = SeedTarget::Containers { households: Vec::new(), workplaces: vec![1] };
+        assert!(eligible_agents(&missing_workplace, &agents, &containers).is_err());
+    }
+
+    #[test]
+    fn test_update_day() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let containers = Containers::new(&[Vec2::new(0.0, 0.0), Vec2::new(50.0, 0.0)], &[], Uniform { transmission_chance: 0.0 });
+        let mut rng = StdRng::seed_from_u64(0);
+
+        // only susceptible agents in the area are seeded, and only on the seed's day
+        let mut agents = Agents::with_households(vec![30; 4], vec![0, 1, 1, 1]);
+        agents.disease_statuses[1].infect();
+        let mut area = seeding(SeedTarget::Area { x: 50.0, y: 0.0, radius: 1.0 }, SeedNumber::All, &agents, &containers);
+        assert!(area.update_day(1, &mut agents, &mut rng).is_empty());
+        assert_eq!(area.update_day(2, &mut agents, &mut rng), vec![2, 3]);
+        assert!((2..4).all(|agent_idx| agents.disease_statuses[agent_idx].state == State::Exposed));
+        assert_eq!(agents.disease_statuses[0].state, State::Susceptible);
+        assert_eq!(area.num_seeded(), 2);
+
+        let mut agents = Agents::with_households(vec![10, 20, 30, 40, 50, 60], vec![0; 6]);
+        let mut exact = seeding(SeedTarget::AgeGroup { min_age: 20, max_age: 50 }, SeedNumber::Exact(2), &agents, &containers);
+        let seeded = exact.update_day(2, &mut agents, &mut rng);
+        assert_eq!(seeded.len(), 2);
+        assert!(seeded.iter().all(|&agent_idx| (1..5).contains(&agent_idx)));
+        // more than are left susceptible seeds all of them
+        let mut exact = seeding(SeedTarget::AgeGroup { min_age: 20, max_age: 50 }, SeedNumber::Exact(10), &agents, &containers);
+        assert_eq!(exact.update_day(2, &mut agents, &mut rng).len(), 2);
+
+        let mut agents = Agents::with_households(vec![30; 4], vec![0; 4]);
+        let mut never = seeding(SeedTarget::Anyone, SeedNumber::Chance(0.0), &agents, &containers);
+        assert!(never.update_day(2, &mut agents, &mut rng).is_empty());
+        let mut always = seeding(SeedTarget::Anyone, SeedNumber::Chance(1.0), &agents, &containers);
+        assert_eq!(always.update_day(2, &mut agents, &mut rng), vec![0, 1, 2, 3]);
+    }
+}
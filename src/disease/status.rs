@@ -4,12 +4,13 @@ use crate::shared::{get_seed_infection_chance, get_time_steps_per_day};
 use crate::shared::types::TimeStep;
 
 // Infection and Disease Progression
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum State {
     Susceptible,
     Exposed,
     Infectious,
     Recovered,
+    Dead,
 }
 
 #[derive(Clone, Copy)]
@@ -23,23 +24,49 @@ impl DiseaseStatus {
         where R: Rng + ?Sized
     {
         if rng.gen::<f32>() > get_seed_infection_chance() {
-            DiseaseStatus {
-                state: State::Susceptible,
-                infected_for: 0,
-            }
-        } else if rng.gen::<f32>() < 0.4 {
+            DiseaseStatus::susceptible()
+        } else {
+            DiseaseStatus::seeded(rng, 0.4)
+        }
+    }
+
+    #[inline]
+    pub fn susceptible() -> DiseaseStatus {
+        DiseaseStatus {
+            state: State::Susceptible,
+            infected_for: 0,
+        }
+    }
+
+    /// An infection introduced at some point into its course, exposed with the given chance and
+    /// otherwise infectious
+    pub fn seeded<R>(rng: &mut R, exposed_share: f32) -> DiseaseStatus
+        where R: Rng + ?Sized
+    {
+        if rng.gen::<f32>() < exposed_share {
             DiseaseStatus {
                 state: State::Exposed,
-                infected_for: (rng.gen_range((0.0)..(2.0)) * get_time_steps_per_day() as f32) as TimeStep,
+                infected_for: (rng.gen_range(0.0..2.0) * get_time_steps_per_day() as f32) as TimeStep,
             }
         } else {
             DiseaseStatus {
                 state: State::Infectious,
-                infected_for: (rng.gen_range((2.0)..(12.0)) * get_time_steps_per_day() as f32) as TimeStep,
+                infected_for: (rng.gen_range(2.0..12.0) * get_time_steps_per_day() as f32) as TimeStep,
             }
         }
     }
 
+    /// How long the agent has been infected for, as of the last time their container was updated
+    #[inline]
+    pub fn infected_for(&self) -> TimeStep {
+        self.infected_for
+    }
+
+    #[inline]
+    pub fn is_infected(&self) -> bool {
+        self.state == State::Exposed || self.state == State::Infectious
+    }
+
     #[inline]
     pub fn infect(&mut self) {
         debug_assert!(self.state == State::Susceptible);
@@ -47,6 +74,11 @@ impl DiseaseStatus {
         self.infected_for = 0;
     }
 
+    #[inline]
+    pub fn die(&mut self) {
+        self.state = State::Dead;
+    }
+
     #[inline]
     pub fn progress_infection(&mut self, time_steps: TimeStep) {
         debug_assert!(self.state == State::Exposed || self.state == State::Infectious);
@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+
+use crate::containers::ContainerType;
+use crate::shared::types::TimeStep;
+
+/// How an agent came to be infected
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InfectionSource {
+    /// Transmitted within a container
+    Container,
+    /// Infected by a seed, see `Seeding`
+    Seeded,
+    /// Imported from outside the modelled region, see `Importation`
+    Imported,
+}
+
+/// A new infection, as recorded in the transmission log
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq)]
+pub struct Transmission {
+    /// The time step the infection was found, which may be after the infection itself as
+    /// containers are updated lazily
+    pub time_step: TimeStep,
+    pub infectee: u32,
+    /// The agent the infection is attributed to, `None` where it's only attributed to the
+    /// container or came from outside of the containers
+    pub infector: Option<u32>,
+    pub source: InfectionSource,
+    pub container_idx: Option<u64>,
+    pub container_type: Option<ContainerType>,
+    /// Always 0, as only a single strain is modelled
+    pub strain: u16,
+}
+
+impl Transmission {
+    /// An infection from outside of the containers, e.g. seeded or imported
+    pub fn external(time_step: TimeStep, infectee: u32, source: InfectionSource) -> Self {
+        Transmission { time_step, infectee, infector: None, source, container_idx: None, container_type: None, strain: 0 }
+    }
+}
+
+/// A count of new infections by where they happened, or where they came from if not in a container
+#[derive(Serialize, Deserialize, Debug, Copy, Clone, Default, PartialEq)]
+pub struct InfectionsBySetting {
+    pub household: usize,
+    pub workplace: usize,
+    pub school: usize,
+    pub hospital: usize,
+    pub seeded: usize,
+    pub imported: usize,
+}
+
+impl InfectionsBySetting {
+    #[inline]
+    pub fn record(&mut self, source: InfectionSource, container_type: Option<ContainerType>) {
+        let count = match (source, container_type) {
+            (InfectionSource::Seeded, _) => { &mut self.seeded }
+            (InfectionSource::Imported, _) => { &mut self.imported }
+            (InfectionSource::Container, Some(ContainerType::Household)) => { &mut self.household }
+            (InfectionSource::Container, Some(ContainerType::Workplace)) => { &mut self.workplace }
+            (InfectionSource::Container, Some(ContainerType::School)) => { &mut self.school }
+            (InfectionSource::Container, Some(ContainerType::Hospital)) => { &mut self.hospital }
+            (InfectionSource::Container, None) => { return; }
+        };
+        *count += 1;
+    }
+
+    #[inline]
+    pub fn add(&mut self, other: InfectionsBySetting) {
+        self.household += other.household;
+        self.workplace += other.workplace;
+        self.school += other.school;
+        self.hospital += other.hospital;
+        self.seeded += other.seeded;
+        self.imported += other.imported;
+    }
+
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.household + self.workplace + self.school + self.hospital + self.seeded + self.imported
+    }
+}
+
+/// An optional log of every new infection, along with a count of the new infections by setting
+/// since it was last reset, which is always kept
+#[derive(Clone, Default)]
+pub struct TransmissionLog {
+    entries: Option<Vec<Transmission>>,
+    new_infections: InfectionsBySetting,
+}
+
+impl TransmissionLog {
+    /// Starts or stops recording infections, stopping discards the existing log
+    pub fn record(&mut self, record: bool) {
+        if !record {
+            self.entries = None;
+        } else if self.entries.is_none() {
+            self.entries = Some(Vec::new());
+        }
+    }
+
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.entries.is_some()
+    }
+
+    #[inline]
+    pub fn push(&mut self, transmission: Transmission) {
+        self.new_infections.record(transmission.source, transmission.container_type);
+        if let Some(entries) = self.entries.as_mut() {
+            entries.push(transmission);
+        }
+    }
+
+    /// The new infections since the counts were last reset
+    #[inline]
+    pub fn new_infections(&self) -> InfectionsBySetting {
+        self.new_infections
+    }
+
+    #[inline]
+    pub fn reset_new_infections(&mut self) {
+        self.new_infections = InfectionsBySetting::default();
+    }
+
+    #[inline]
+    pub fn entries(&self) -> Option<&[Transmission]> {
+        self.entries.as_deref()
+    }
+
+    /// Takes the entries recorded so far, leaving the log empty but still recording
+    pub fn drain(&mut self) -> Vec<Transmission> {
+        self.entries.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new_infections_by_setting() {
+        let mut log = TransmissionLog::default();
+        log.push(Transmission::external(0, 1, InfectionSource::Seeded));
+        log.push(Transmission {
+            time_step: 3,
+            infectee: 2,
+            infector: Some(1),
+            source: InfectionSource::Container,
+            container_idx: Some(10),
+            container_type: Some(ContainerType::School),
+            strain: 0,
+        });
+
+        assert!(log.entries().is_none());
+        assert_eq!(log.new_infections(), InfectionsBySetting { school: 1, seeded: 1, ..InfectionsBySetting::default() });
+        log.reset_new_infections();
+        assert_eq!(log.new_infections().total(), 0);
+    }
+}
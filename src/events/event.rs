@@ -1,12 +1,11 @@
-use fast_paths::{FastGraph, PathCalculator};
 use nonmax::NonMaxU64;
-use rand::{Rng, thread_rng};
 
-use crate::agents::Agents;
+use crate::agents::{Agents, Journey, Location};
 use crate::containers::Containers;
 use crate::disease::MixingStrategy;
+use crate::interventions::Interventions;
 use crate::events::event::EventType::{EnterContainer, Travel};
-use crate::routing::{calculate_direct_commute_time, calculate_public_transit_commute_time, DirectRoutingType, GranularGrid, RoutingType};
+use crate::routing::{calculate_direct_commute_time, calculate_public_transit_commute_time, DirectRoutingType, Router, RoutingType};
 use crate::routing::DirectRoutingType::Driving;
 use crate::shared::types::TimeStep;
 use crate::shared::get_time_steps_per_day;
@@ -17,6 +16,9 @@ pub struct Event {
     // start_timestep: TimeStep,
     pub end_time_step: TimeStep,
     pub event_type: EventType,
+    /// The agent's event generation when the event was queued, the event being cancelled if the
+    /// agent's generation has moved on by the time it's due
+    pub generation: u32,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -32,49 +34,123 @@ pub struct TravelType {
     pub routing_type: RoutingType,
 }
 
+/// What handling an event leads to, either the agent's next event, if any, or them setting off
+pub enum Handled {
+    Next(Option<Event>),
+    Departing(Departure),
+}
+
+/// An agent who's left a container by a resolved leg, whose travel time is only known once the
+/// number of agents setting off to drive in the same time step is
+#[derive(Debug, Copy, Clone)]
+pub struct Departure {
+    agent_idx: u32,
+    generation: u32,
+    time_step: TimeStep,
+    from_container_idx: NonMaxU64,
+    to_container_idx: NonMaxU64,
+    routing_type: RoutingType,
+    free_flow_time: TimeStep,
+}
+
+impl Departure {
+    #[inline]
+    pub fn is_driving(&self) -> bool {
+        self.routing_type == RoutingType::Direct(Driving)
+    }
+
+    /// Puts the agent in transit, slowed by congestion if they're driving, returning the event of
+    /// them arriving
+    pub fn travel(self, agents: &mut Agents, router: &mut Router) -> Event {
+        let commute_time = if self.is_driving() {
+            router.congestion.driving_time(self.free_flow_time)
+        } else {
+            self.free_flow_time
+        };
+        agents.locations.set(self.agent_idx, Location::InTransit(Journey {
+            from_container_idx: self.from_container_idx,
+            to_container_idx: self.to_container_idx,
+            routing_type: self.routing_type,
+            departure_time_step: self.time_step,
+            arrival_time_step: self.time_step + commute_time,
+        }), self.time_step);
+        Event {
+            agent_idx: self.agent_idx,
+            generation: self.generation,
+            end_time_step: self.time_step + commute_time,
+            event_type: EnterContainer(self.to_container_idx),
+        }
+    }
+}
+
 impl Event {
     #[inline]
-    pub fn handle<M>(self, agents: &mut Agents, containers: &mut Containers<M>, transit_grid: &GranularGrid<usize>,
-                     fast_graph: &FastGraph, transit_path_calculator: &mut PathCalculator) -> Option<Event>
+    pub fn handle<M>(self, agents: &mut Agents, containers: &mut Containers<M>, router: &mut Router, interventions: &Interventions) -> Handled
         where M: MixingStrategy {
         match self.event_type {
             EventType::EnterContainer(from_container_idx) => {
                 containers.push_inhabitant(from_container_idx.get(), self.agent_idx, self.end_time_step, agents);
+                agents.locations.set(self.agent_idx, Location::InContainer(from_container_idx.get()), self.end_time_step);
 
-                let occupation_container_idx = agents.occupational_container[self.agent_idx as usize].unwrap();
-                let to_container_idx = if from_container_idx != occupation_container_idx { occupation_container_idx } else { NonMaxU64::new(agents.household_container[self.agent_idx as usize]).unwrap() };
+                // agents go back and forth between home and their occupational container, anywhere else
+                // (or for those without an occupation) it's up to whatever sent them there to queue what's next
+                let household_container_idx = agents.household_container[self.agent_idx as usize];
+                let to_container_idx = match agents.occupational_container[self.agent_idx as usize] {
+                    Some(occupation_container_idx) if from_container_idx == occupation_container_idx => {
+                        NonMaxU64::new(household_container_idx).unwrap()
+                    }
+                    Some(occupation_container_idx) if from_container_idx.get() == household_container_idx => { occupation_container_idx }
+                    _ => { return Handled::Next(None); }
+                };
 
-                Some(Event {
+                Handled::Next(Some(Event {
                     agent_idx: self.agent_idx,
+                    generation: self.generation,
                     end_time_step: self.end_time_step + (get_time_steps_per_day() / 2),
                     event_type: Travel(TravelType {
                         from_container_idx,
                         to_container_idx,
-                        // TODO Change Transit chance to non-zero when transit containers are implemented
-                        routing_type: if thread_rng().gen::<f32>() < 1.0 { RoutingType::Direct(Driving) } else { RoutingType::Transit },
+                        routing_type: router.commute_cache.get(self.agent_idx).map_or(RoutingType::Direct(Driving), |commute| commute.routing_type),
                     }),
-                })
+                }))
             }
             EventType::Travel(travel_type) => {
+                if travel_type.from_container_idx.get() == agents.household_container[self.agent_idx as usize]
+                    && interventions.stays_home(self.agent_idx, containers.get(travel_type.to_container_idx.get()).unwrap()) {
+                    // Try again at the same time tomorrow
+                    return Handled::Next(Some(Event {
+                        end_time_step: self.end_time_step + get_time_steps_per_day(),
+                        ..self
+                    }));
+                }
+
                 containers.remove_inhabitant(travel_type.from_container_idx.get(), self.agent_idx, self.end_time_step, agents);
-                let mut commute_time = match travel_type.routing_type {
-                    RoutingType::Transit => {
-                        if let Ok(commute_time) = calculate_public_transit_commute_time(containers, transit_grid, transit_path_calculator, fast_graph, travel_type.from_container_idx, travel_type.to_container_idx) {
-                            commute_time
+                let cached_leg = router.commute_cache.get_leg(agents, self.agent_idx, travel_type.from_container_idx, travel_type.to_container_idx);
+                let (mut commute_time, routing_type) = match (cached_leg, travel_type.routing_type) {
+                    (Some(leg), _) => { (leg.time, leg.routing_type) }
+                    (None, RoutingType::Transit) => {
+                        if let Ok(commute_time) = calculate_public_transit_commute_time(containers, router.transit_stops, &mut router.transit_path_calculator, router.fast_graph, travel_type.from_container_idx, travel_type.to_container_idx) {
+                            (commute_time, RoutingType::Transit)
                         } else {
-                            calculate_direct_commute_time(containers, DirectRoutingType::Driving, travel_type.from_container_idx, travel_type.to_container_idx)
+                            (calculate_direct_commute_time(containers, DirectRoutingType::Driving, travel_type.from_container_idx, travel_type.to_container_idx),
+                             RoutingType::Direct(Driving))
                         }
                     }
-                    RoutingType::Direct(direct_routing_type) => {
-                        calculate_direct_commute_time(containers, direct_routing_type, travel_type.from_container_idx, travel_type.to_container_idx)
+                    (None, RoutingType::Direct(direct_routing_type)) => {
+                        (calculate_direct_commute_time(containers, direct_routing_type, travel_type.from_container_idx, travel_type.to_container_idx),
+                         RoutingType::Direct(direct_routing_type))
                     }
                 };
 
                 if commute_time == 0 { commute_time += 1 };
-                Some(Event {
+                Handled::Departing(Departure {
                     agent_idx: self.agent_idx,
-                    end_time_step: self.end_time_step + commute_time,
-                    event_type: EnterContainer(travel_type.to_container_idx),
+                    generation: self.generation,
+                    time_step: self.end_time_step,
+                    from_container_idx: travel_type.from_container_idx,
+                    to_container_idx: travel_type.to_container_idx,
+                    routing_type,
+                    free_flow_time: commute_time,
                 })
             }
         }
@@ -1,48 +1,58 @@
 use std::collections::VecDeque;
 
-use fast_paths::{FastGraph, PathCalculator};
-
 use crate::agents::Agents;
 use crate::containers::Containers;
 use crate::disease::MixingStrategy;
-use crate::events::event::Event;
-use crate::routing::GranularGrid;
+use crate::interventions::Interventions;
+use crate::events::event::{Event, Handled};
+use crate::routing::Router;
 use crate::shared::types::TimeStep;
 
 pub type EventIndex = VecDeque<Vec<Event>>;
 
 pub trait Update {
-    fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, transit_grid: &GranularGrid<usize>,
-                 fast_graph: &FastGraph, transit_path_calculator: &mut PathCalculator) where M: MixingStrategy;
+    fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, router: &mut Router,
+                 interventions: &Interventions, generations: &[u32]) where M: MixingStrategy;
 }
 
 impl Update for EventIndex {
-    fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, transit_grid: &GranularGrid<usize>,
-                 fast_graph: &FastGraph, transit_path_calculator: &mut PathCalculator) where M: MixingStrategy {
+    fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, router: &mut Router,
+                 interventions: &Interventions, generations: &[u32]) where M: MixingStrategy {
         if let Some(mut events) = self.pop_front() {
-            events.drain(..).for_each(|event| {
+            // drop the events that were cancelled after being queued
+            events.retain(|event| event.generation == generations[event.agent_idx as usize]);
+
+            let mut next_events = Vec::with_capacity(events.len());
+            let mut departures = Vec::new();
+            for event in events.drain(..) {
                 debug_assert!(event.end_time_step == time_step);
-                if let Some(next_event) = event.handle(agents, containers, transit_grid, fast_graph, transit_path_calculator) {
-                    let index_of_next_time = (next_event.end_time_step - time_step - 1) as usize; // minus one because we've already popped this time_step's index
-                    self.get_mut_or_grow(index_of_next_time).unwrap().push(next_event);
+                match event.handle(agents, containers, router, interventions) {
+                    Handled::Next(next_event) => { next_events.extend(next_event); }
+                    Handled::Departing(departure) => { departures.push(departure); }
                 }
-            });
+            }
+            // the volume of traffic is only known once everyone setting off has chosen how, e.g.
+            // those staying home don't set off and transit journeys without a route are driven
+            let num_driving = departures.iter().filter(|departure| departure.is_driving()).count();
+            router.congestion.begin_time_step(num_driving as u32);
+            next_events.extend(departures.into_iter().map(|departure| departure.travel(agents, router)));
+
+            for next_event in next_events {
+                let index_of_next_time = (next_event.end_time_step - time_step - 1) as usize; // minus one because we've already popped this time_step's index
+                self.get_mut_or_grow(index_of_next_time).unwrap().push(next_event);
+            }
+        } else {
+            router.congestion.begin_time_step(0);
         }
+        router.congestion.end_time_step();
     }
 }
 
 pub trait VecDequeMutExt<T: Default> {
-    fn get_or_grow(&mut self, index: usize) -> Option<&T>;
     fn get_mut_or_grow(&mut self, index: usize) -> Option<&mut T>;
 }
 
 impl<T: Default> VecDequeMutExt<T> for VecDeque<T> {
-    fn get_or_grow(&mut self, index: usize) -> Option<&T> {
-        if index >= self.len() {
-            self.resize_with(index + 1, Default::default);
-        }
-        self.get(index)
-    }
     fn get_mut_or_grow(&mut self, index: usize) -> Option<&mut T> {
         if index >= self.len() {
             self.resize_with(index + 1, Default::default);
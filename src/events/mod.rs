@@ -1,50 +1,159 @@
-use fast_paths::{FastGraph, PathCalculator};
-use rand::{Rng, thread_rng};
+use nonmax::NonMaxU64;
+use rand::Rng;
 
 use crate::agents::Agents;
 use crate::containers::Containers;
 use crate::disease::MixingStrategy;
-pub use crate::events::event::{Event, EventType};
+use crate::interventions::Interventions;
+pub use crate::events::event::{Event, EventType, TravelType};
 use crate::events::event_index::{EventIndex, Update, VecDequeMutExt};
-use crate::routing::GranularGrid;
-use crate::shared::get_time_steps_per_day;
+use crate::routing::{DirectRoutingType, Router, RoutingType};
+use crate::routing::commute::CommuteCache;
+use crate::shared::{get_time_steps_per_day, RngStream, seeded_rng};
 use crate::shared::types::TimeStep;
 
 mod event;
 mod event_index;
 
+/// Identifies the events queued for an agent, staying valid until the agent's events are cancelled.
+///
+/// Handling an event queues the agent's next one under the same handle, so a handle follows an
+/// agent's routine rather than a single event.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct EventHandle {
+    agent_idx: u32,
+    generation: u32,
+}
+
+impl EventHandle {
+    #[inline]
+    pub fn agent_idx(&self) -> u32 {
+        self.agent_idx
+    }
+}
+
+/// # Events
+/// The events queued for every agent, indexed by the time step they're due.
+///
+/// Any of an agent's queued events can be cancelled and replaced, e.g. to send them home or to
+/// hospital, by whatever is changing their routine. An agent is always in exactly one container or
+/// in transit, which `Containers::verify_locations` checks.
 #[derive(Clone)]
 pub struct Events {
     event_index: EventIndex,
+    /// The time step the front of the event index is for
+    next_time_step: TimeStep,
+    /// Each agent's current event generation, incremented to cancel their queued events
+    generations: Vec<u32>,
 }
 
 impl Events {
-    pub fn new(agents: &mut Agents) -> Self {
-        let mut rng = thread_rng();
+    pub fn new(agents: &Agents, commute_cache: &CommuteCache) -> Self {
+        let mut rng = seeded_rng(RngStream::MorningCommutes, &[]);
         let mut event_index = EventIndex::default();
 
-        agents.occupational_container.iter()
+        agents.household_container.iter()
+            .zip(agents.occupational_container.iter())
             .enumerate()
-            .filter(|(_, &container_idx)| container_idx.is_some())
-            .map(|(agent_idx, container_idx)| {
-                Event {
-                    agent_idx: agent_idx as u32,
-                    end_time_step: tmp_weighted_commute_time(&mut rng),
-                    event_type: EventType::EnterContainer(container_idx.unwrap()),
-                }
+            .filter_map(|(agent_idx, (&household_idx, &occupational_idx))| {
+                occupational_idx.map(|occupational_idx| {
+                    Event {
+                        agent_idx: agent_idx as u32,
+                        generation: 0,
+                        end_time_step: tmp_weighted_commute_time(&mut rng),
+                        event_type: EventType::Travel(TravelType {
+                            from_container_idx: NonMaxU64::new(household_idx).unwrap(),
+                            to_container_idx: occupational_idx,
+                            routing_type: commute_cache.get(agent_idx as u32)
+                                .map_or(RoutingType::Direct(DirectRoutingType::Driving), |commute| commute.routing_type),
+                        }),
+                    }
+                })
             })
             .for_each(|event| {
                 event_index.get_mut_or_grow(event.end_time_step as usize).unwrap().push(event);
             });
 
         Events {
-            event_index
+            event_index,
+            next_time_step: 0,
+            generations: vec![0; agents.num_agents as usize],
+        }
+    }
+
+    pub fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, router: &mut Router,
+                     interventions: &Interventions) where M: MixingStrategy {
+        debug_assert!(time_step == self.next_time_step);
+        self.event_index.update(time_step, agents, containers, router, interventions, &self.generations);
+        self.next_time_step = time_step + 1;
+    }
+
+    /// The earliest time step an event can be queued for
+    #[inline]
+    pub fn next_time_step(&self) -> TimeStep {
+        self.next_time_step
+    }
+
+    /// The handle of the events currently queued for an agent
+    #[inline]
+    pub fn handle_of(&self, agent_idx: u32) -> EventHandle {
+        EventHandle { agent_idx, generation: self.generations[agent_idx as usize] }
+    }
+
+    #[inline]
+    pub fn is_cancelled(&self, handle: EventHandle) -> bool {
+        self.generations[handle.agent_idx as usize] != handle.generation
+    }
+
+    /// Cancels all of an agent's queued events
+    #[inline]
+    pub fn cancel(&mut self, agent_idx: u32) {
+        self.generations[agent_idx as usize] += 1;
+    }
+
+    /// Cancels the events of a handle, returning false if they'd already been cancelled
+    #[inline]
+    pub fn cancel_handle(&mut self, handle: EventHandle) -> bool {
+        let cancelled = !self.is_cancelled(handle);
+        if cancelled {
+            self.cancel(handle.agent_idx);
         }
+        cancelled
     }
 
-    pub fn update<M>(&mut self, time_step: TimeStep, agents: &mut Agents, containers: &mut Containers<M>, transit_grid: &GranularGrid<usize>,
-                     fast_graph: &FastGraph, transit_path_calculator: &mut PathCalculator) where M: MixingStrategy {
-        self.event_index.update(time_step, agents, containers, transit_grid, fast_graph, transit_path_calculator);
+    /// Queues an event for an agent alongside any already queued, to be handled at its
+    /// `end_time_step` which mustn't have passed
+    pub fn push(&mut self, mut event: Event) -> EventHandle {
+        assert!(event.end_time_step >= self.next_time_step, "Can't queue an event for a time step that's already passed");
+        event.generation = self.generations[event.agent_idx as usize];
+        let index = (event.end_time_step - self.next_time_step) as usize;
+        self.event_index.get_mut_or_grow(index).unwrap().push(event);
+        self.handle_of(event.agent_idx)
+    }
+
+    /// Cancels an agent's queued events and queues the given one in their place
+    #[inline]
+    pub fn reschedule(&mut self, event: Event) -> EventHandle {
+        self.cancel(event.agent_idx);
+        self.push(event)
+    }
+
+    /// Queues an agent's commute from their household to their occupational container the morning
+    /// after the given time step, returning `None` for agents without an occupation
+    pub fn push_morning_commute(&mut self, agents: &Agents, commute_cache: &CommuteCache, agent_idx: u32, time_step: TimeStep) -> Option<EventHandle> {
+        let to_container_idx = agents.occupational_container[agent_idx as usize]?;
+        let next_day_start = (time_step / get_time_steps_per_day() + 1) * get_time_steps_per_day();
+        Some(self.push(Event {
+            agent_idx,
+            generation: 0,
+            end_time_step: next_day_start + tmp_weighted_commute_time(&mut seeded_rng(RngStream::MorningCommutes, &[agent_idx as u64, time_step as u64])),
+            event_type: EventType::Travel(TravelType {
+                from_container_idx: NonMaxU64::new(agents.household_container[agent_idx as usize]).unwrap(),
+                to_container_idx,
+                routing_type: commute_cache.get(agent_idx)
+                    .map_or(RoutingType::Direct(DirectRoutingType::Driving), |commute| commute.routing_type),
+            }),
+        }))
     }
 }
 
@@ -59,4 +168,137 @@ fn tmp_weighted_commute_time<R>(rng: &mut R) -> TimeStep
     let time_steps_range = (3.5 * time_steps_per_hour as f32) as TimeStep;
 
     earliest + (rng.gen::<f32>() * time_steps_range as f32) as TimeStep
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::ops::Range;
+
+    use fast_paths::{FastGraph, InputGraph};
+
+    use crate::agents::Location;
+    use crate::disease::Uniform;
+    use crate::routing::KdTree;
+    use crate::routing::congestion::Congestion;
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+    use crate::Vec2;
+
+    use super::*;
+
+    struct World {
+        agents: Agents,
+        containers: Containers<Uniform>,
+        commute_cache: CommuteCache,
+        transit_stops: KdTree<usize>,
+        fast_graph: FastGraph,
+        congestion: Congestion,
+        interventions: Interventions,
+    }
+
+    impl World {
+        /// A household of two agents, the first working at the only workplace and the second
+        /// without an occupation
+        fn new() -> Self {
+            GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+            let household_positions = [Vec2::new(0.0, 0.0)];
+            let workplace_positions = [Vec2::new(0.0, 5000.0)];
+            let mut containers = Containers::new(&household_positions, &workplace_positions, Uniform { transmission_chance: 0.0 });
+            let mut agents = Agents::with_households(vec![30, 30], vec![0, 0]);
+            agents.occupational_container[0] = NonMaxU64::new(containers.get_workplace_idx(0));
+            containers.push_inhabitant_no_update(0, 0);
+            containers.push_inhabitant_no_update(0, 1);
+
+            let transit_stops = KdTree::new(vec![(household_positions[0], 0), (workplace_positions[0], 1)]);
+            let mut input_graph = InputGraph::new();
+            input_graph.add_edge(0, 1, 3);
+            input_graph.freeze();
+            let fast_graph = fast_paths::prepare(&input_graph);
+            let commute_cache = CommuteCache::new(&agents, &containers, &transit_stops, &fast_graph);
+            World {
+                agents,
+                containers,
+                commute_cache,
+                transit_stops,
+                fast_graph,
+                congestion: Congestion::new(None, 0.15, 4.0),
+                interventions: Interventions::default(),
+            }
+        }
+
+        /// Updates the events over the given time steps, checking every agent is in exactly one
+        /// container or in transit after each
+        fn update(&mut self, events: &mut Events, time_steps: Range<TimeStep>) {
+            for time_step in time_steps {
+                let mut router = Router::new(&self.commute_cache, &mut self.congestion, &self.transit_stops, &self.fast_graph);
+                events.update(time_step, &mut self.agents, &mut self.containers, &mut router, &self.interventions);
+                self.containers.verify_locations(&self.agents.locations).unwrap();
+            }
+        }
+
+        fn workplace_idx(&self) -> u64 {
+            self.containers.get_workplace_idx(0)
+        }
+    }
+
+    #[test]
+    fn test_cancelled_events_are_skipped() {
+        let mut world = World::new();
+        let mut events = Events::new(&world.agents, &world.commute_cache);
+        let handle = events.handle_of(0);
+        events.cancel(0);
+        assert!(events.is_cancelled(handle));
+        assert!(!events.cancel_handle(handle));
+
+        // without the morning commute nothing is left queued for the agent
+        for time_step in 0..2 * get_time_steps_per_day() {
+            world.update(&mut events, time_step..time_step + 1);
+            assert_eq!(world.agents.locations.get(0), Location::InContainer(0));
+        }
+    }
+
+    #[test]
+    fn test_reschedule_replaces_queued_events() {
+        let mut world = World::new();
+        let mut events = Events::new(&world.agents, &world.commute_cache);
+        let morning_commute = events.handle_of(0);
+        let handle = events.reschedule(Event {
+            agent_idx: 0,
+            generation: 0,
+            end_time_step: 1,
+            event_type: EventType::Travel(TravelType {
+                from_container_idx: NonMaxU64::new(0).unwrap(),
+                to_container_idx: NonMaxU64::new(world.workplace_idx()).unwrap(),
+                routing_type: RoutingType::Direct(DirectRoutingType::Driving),
+            }),
+        });
+        assert!(events.is_cancelled(morning_commute));
+        assert!(!events.is_cancelled(handle));
+
+        world.update(&mut events, 0..2);
+        assert!(matches!(world.agents.locations.get(0), Location::InTransit(_)));
+        // the morning commute would have the agent leave home again while they're at work
+        world.update(&mut events, 2..get_time_steps_per_day() / 2);
+        assert_eq!(world.agents.locations.get(0), Location::InContainer(world.workplace_idx()));
+        // the rescheduled event's handle follows the rest of their routine
+        events.cancel_handle(handle);
+        world.update(&mut events, get_time_steps_per_day() / 2..2 * get_time_steps_per_day());
+        assert_eq!(world.agents.locations.get(0), Location::InContainer(world.workplace_idx()));
+    }
+
+    #[test]
+    fn test_push_morning_commute() {
+        let mut world = World::new();
+        let mut events = Events::new(&world.agents, &world.commute_cache);
+        events.cancel(0);
+        assert!(events.push_morning_commute(&world.agents, &world.commute_cache, 1, 0).is_none());
+        let handle = events.push_morning_commute(&world.agents, &world.commute_cache, 0, 0).unwrap();
+        assert!(!events.is_cancelled(handle));
+
+        // the commute is the next morning's rather than the current day's
+        let tspd = get_time_steps_per_day();
+        world.update(&mut events, 0..tspd);
+        assert_eq!(world.agents.locations.get(0), Location::InContainer(0));
+        world.update(&mut events, tspd..tspd + tspd * 3 / 4);
+        assert_eq!(world.agents.locations.get(0), Location::InContainer(world.workplace_idx()));
+        assert_eq!(world.agents.locations.get(1), Location::InContainer(0));
+    }
+}
@@ -2,38 +2,24 @@
 extern crate flatbuffers;
 
 pub use model_generated::outbreak_sim::model::{
-    Agents,
     Bounds,
     root_as_model,
-    Households,
     Model,
     TransitGraph,
-    TransitEdge,
-    TransitNode,
-    TransitEdgeRides,
-    TransitRide,
     Vec2,
-    Workplaces,
 };
+use std::error::Error;
 use std::path::Path;
 
 // import the generated code
-#[allow(dead_code, unused_imports)]
+#[allow(dead_code, unused_imports, clippy::all)]
 #[path = "./generated/model_generated.rs"]
 mod model_generated;
 
-pub fn read_buffer(path: &Path) -> Vec<u8> {
-    match std::fs::read(path) {
-        Ok(bytes) => {
-            bytes
-        }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::PermissionDenied {
-                eprintln!("please run again with appropriate permissions.");
-            } else if e.kind() == std::io::ErrorKind::NotFound {
-                eprintln!("Couldn't find file: {}", path.display());
-            }
-            panic!("{}", e);
-        }
-    }
+pub fn read_buffer(path: &Path) -> Result<Vec<u8>, Box<dyn Error>> {
+    std::fs::read(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::PermissionDenied => { format!("{} couldn't be read, please run again with appropriate permissions", path.display()).into() }
+        std::io::ErrorKind::NotFound => { format!("couldn't find file: {}", path.display()).into() }
+        _ => { e.into() }
+    })
 }
\ No newline at end of file
@@ -1,8 +1,10 @@
 use std::cmp::{min, max};
 
 // Heavily inspired by https://github.com/parasyte/pixels/blob/master/examples/conway/src/main.rs
-use outbreak_sim::{disease, Sim};
+use outbreak_sim::{disease, Sim, Vec2};
+use outbreak_sim::agents::Location;
 use outbreak_sim::disease::MixingStrategy;
+use outbreak_sim::shared::types::TimeStep;
 
 #[derive(Clone, Copy, Debug, Default)]
 struct Cell {
@@ -13,6 +15,29 @@ struct Cell {
     num_recovered: u32
 }
 
+impl Cell {
+    #[inline]
+    fn add(&mut self, state: disease::State) {
+        match state {
+            disease::State::Susceptible => {
+                self.num_susceptible += 1;
+            },
+            disease::State::Exposed => {
+                self.num_exposed += 1;
+            }
+            disease::State::Infectious => {
+                self.num_infectious += 1;
+            },
+            disease::State::Recovered => {
+                self.num_recovered += 1;
+            }
+            // the dead have been removed from the containers so are never drawn
+            disease::State::Dead => { return; }
+        }
+        self.total += 1;
+    }
+}
+
 pub struct WorldGrid {
     cells: Vec<Cell>,
     width: usize,
@@ -38,7 +63,7 @@ impl WorldGrid {
         }
     }
 
-    pub fn update<M: MixingStrategy>(&mut self, sim: &Sim<M>) {
+    pub fn update<M: MixingStrategy>(&mut self, sim: &Sim<M>, time_step: TimeStep) {
         self.max_at_cell = 0;
         for y in 0..self.height {
             for x in 0..self.width {
@@ -49,34 +74,35 @@ impl WorldGrid {
         }
         for container_idx in 0..sim.containers.len() {
             let container = sim.containers.get(container_idx as u64).unwrap();
-            let mut x = ((container.pos.x() / sim.bounds.max().x()) * self.width as f32) as usize;
-            let mut y = ((((container.pos.y() / sim.bounds.max().y()) - 1.0) * self.height as f32).abs()) as usize;
-            x = min(x, self.width - 1); y = min(y, self.height - 1);
-            let idx = x + y * self.width;
+            let idx = self.cell_idx(container.pos, sim);
 
             let cell_stats = &mut self.scratch_cells[idx];
             for &agent_idx in container.inhabitants.iter() {
-                match sim.agents.disease_statuses[agent_idx as usize].state {
-                    disease::State::Susceptible => {
-                        cell_stats.num_susceptible += 1;
-                    },
-                    disease::State::Exposed => {
-                        cell_stats.num_exposed += 1;
-                    }
-                    disease::State::Infectious => {
-                        cell_stats.num_infectious += 1;
-                    },
-                    disease::State::Recovered => {
-                        cell_stats.num_recovered += 1;
-                    }
-                }
+                cell_stats.add(sim.agents.disease_statuses[agent_idx as usize].state);
             }
-            cell_stats.total += container.inhabitants.len() as u32;
             self.max_at_cell = max(cell_stats.total, self.max_at_cell);
         }
+        // Commuters are drawn at their interpolated position along their route
+        for (agent_idx, location) in sim.agents.locations.iter().enumerate() {
+            if let Location::InTransit(_) = location {
+                let idx = self.cell_idx(sim.agent_position(agent_idx as u32, time_step), sim);
+
+                let cell_stats = &mut self.scratch_cells[idx];
+                cell_stats.add(sim.agents.disease_statuses[agent_idx].state);
+                self.max_at_cell = max(cell_stats.total, self.max_at_cell);
+            }
+        }
         std::mem::swap(&mut self.scratch_cells, &mut self.cells);
     }
 
+    #[inline]
+    fn cell_idx<M: MixingStrategy>(&self, pos: Vec2, sim: &Sim<M>) -> usize {
+        let mut x = ((pos.x() / sim.bounds.max().x()) * self.width as f32) as usize;
+        let mut y = ((((pos.y() / sim.bounds.max().y()) - 1.0) * self.height as f32).abs()) as usize;
+        x = min(x, self.width - 1); y = min(y, self.height - 1);
+        x + y * self.width
+    }
+
     pub fn draw(&self, screen: &mut [u8]) {
         debug_assert_eq!(screen.len(), 4 * self.cells.len());
         for (c, pix) in self.cells.iter().zip(screen.chunks_exact_mut(4)) {
@@ -0,0 +1,256 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::Agents;
+use crate::shared::{get_time_steps_per_day, RngStream, seeded_rng};
+
+/// Symptomatic agents isolating at home, optionally along with the rest of their household
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct IsolationPolicy {
+    /// The share of infections that develop symptoms
+    pub symptomatic_share: f32,
+    /// Days from infection to the onset of symptoms
+    pub symptom_onset_days: f32,
+    /// The share of symptomatic agents who isolate
+    pub compliance: f32,
+    /// Days from the onset of symptoms to starting to isolate
+    #[serde(default)]
+    pub delay_days: f32,
+    pub isolation_days: u32,
+    /// Whether the household members of an isolating agent quarantine alongside them
+    #[serde(default)]
+    pub household_quarantine: bool,
+    /// The share of household members who quarantine
+    #[serde(default = "default_household_compliance")]
+    pub household_compliance: f32,
+    #[serde(default)]
+    pub quarantine_days: u32,
+}
+
+fn default_household_compliance() -> f32 { 1.0 }
+
+/// # Isolation
+/// Which agents are staying at home because they're symptomatic or quarantining, and until when.
+///
+/// Whether an agent develops symptoms and whether they comply are drawn once at the start, as agents
+/// can't be reinfected.
+#[derive(Clone)]
+pub struct Isolation {
+    policy: IsolationPolicy,
+    symptomatic: Vec<bool>,
+    compliance_draws: Vec<f32>,
+    /// Whether each agent's symptoms have appeared
+    has_symptoms: Vec<bool>,
+    /// Whether each agent has already isolated because of their own symptoms
+    has_isolated: Vec<bool>,
+    /// The day each agent stops staying at home, `None` if they're not
+    staying_home_until: Vec<Option<u32>>,
+    household_container: Vec<u64>,
+    household_members: Vec<Vec<u32>>,
+}
+
+impl Isolation {
+    pub fn new(policy: IsolationPolicy, agents: &Agents) -> Self {
+        let mut rng = seeded_rng(RngStream::Isolation, &[]);
+        let num_agents = agents.num_agents as usize;
+
+        let num_households = agents.household_container.iter().max().map_or(0, |&idx| idx as usize + 1);
+        let mut household_members = vec![Vec::new(); num_households];
+        for (agent_idx, &household_idx) in agents.household_container.iter().enumerate() {
+            household_members[household_idx as usize].push(agent_idx as u32);
+        }
+
+        Isolation {
+            symptomatic: (0..num_agents).map(|_| rng.gen::<f32>() < policy.symptomatic_share).collect(),
+            compliance_draws: (0..num_agents).map(|_| rng.gen::<f32>()).collect(),
+            has_symptoms: vec![false; num_agents],
+            has_isolated: vec![false; num_agents],
+            staying_home_until: vec![None; num_agents],
+            household_container: agents.household_container.clone(),
+            household_members,
+            policy,
+        }
+    }
+
+    #[inline]
+    pub fn policy(&self) -> &IsolationPolicy {
+        &self.policy
+    }
+
+    #[inline]
+    pub fn is_staying_home(&self, agent_idx: u32) -> bool {
+        self.staying_home_until[agent_idx as usize].is_some()
+    }
+
+    #[inline]
+    pub fn num_staying_home(&self) -> usize {
+        self.staying_home_until.iter().filter(|until| until.is_some()).count()
+    }
+
+    /// Makes an agent stay at home until at least the given day, returning whether they weren't already
+    pub fn stay_home(&mut self, agent_idx: u32, until_day: u32) -> bool {
+        let until = &mut self.staying_home_until[agent_idx as usize];
+        let started = until.is_none();
+        *until = Some(until.map_or(until_day, |until| until.max(until_day)));
+        started
+    }
+
+    #[inline]
+    pub fn complies(&self, agent_idx: u32) -> bool {
+        self.compliance_draws[agent_idx as usize] < self.policy.compliance
+    }
+
+    /// Isolates an agent from the given day, along with their household if they quarantine,
+    /// adding those who've started staying at home to `started`
+    pub fn isolate(&mut self, agent_idx: u32, day: u32, started: &mut Vec<u32>) {
+        if self.stay_home(agent_idx, day + self.policy.isolation_days) {
+            started.push(agent_idx);
+        }
+        if self.policy.household_quarantine {
+            let household_idx = self.household_container[agent_idx as usize] as usize;
+            let compliant_members: Vec<u32> = self.household_members[household_idx].iter().copied()
+                .filter(|&member| member != agent_idx && self.compliance_draws[member as usize] < self.policy.household_compliance)
+                .collect();
+            for member in compliant_members {
+                if self.stay_home(member, day + self.policy.quarantine_days) {
+                    started.push(member);
+                }
+            }
+        }
+    }
+
+    /// The agents whose symptoms have appeared since the last call, whether or not they isolate
+    pub fn newly_symptomatic(&mut self, agents: &Agents) -> Vec<u32> {
+        let onset = (self.policy.symptom_onset_days * get_time_steps_per_day() as f32) as u32;
+        (0..agents.num_agents).filter(|&agent_idx| {
+            let idx = agent_idx as usize;
+            let status = &agents.disease_statuses[idx];
+            let appeared = !self.has_symptoms[idx] && self.symptomatic[idx] && status.is_infected() && status.infected_for() >= onset;
+            self.has_symptoms[idx] |= appeared;
+            appeared
+        }).collect()
+    }
+
+    /// Releases the agents whose isolation or quarantine has ended and starts it for the agents
+    /// whose symptoms have appeared, returning the agents who've started staying at home.
+    ///
+    /// The agents' disease statuses should be up to date as of the start of the day.
+    pub fn update_day(&mut self, day: u32, agents: &Agents) -> Vec<u32> {
+        for until in self.staying_home_until.iter_mut() {
+            if until.is_some_and(|until_day| day >= until_day) {
+                *until = None;
+            }
+        }
+
+        let isolate_after = ((self.policy.symptom_onset_days + self.policy.delay_days) * get_time_steps_per_day() as f32) as u32;
+        let mut started = Vec::new();
+        for agent_idx in 0..agents.num_agents {
+            let idx = agent_idx as usize;
+            let status = &agents.disease_statuses[idx];
+            if self.has_isolated[idx] || !self.symptomatic[idx] || !status.is_infected() || status.infected_for() < isolate_after {
+                continue;
+            }
+            self.has_isolated[idx] = true;
+            if self.complies(agent_idx) {
+                self.isolate(agent_idx, day, &mut started);
+            }
+        }
+        started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+
+    use super::*;
+
+    fn policy() -> IsolationPolicy {
+        IsolationPolicy {
+            symptomatic_share: 1.0,
+            symptom_onset_days: 2.0,
+            compliance: 1.0,
+            delay_days: 1.0,
+            isolation_days: 7,
+            household_quarantine: false,
+            household_compliance: 1.0,
+            quarantine_days: 14,
+        }
+    }
+
+    /// The first two agents share a household, the third lives on their own
+    fn agents() -> Agents {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        Agents::with_households(vec![30; 3], vec![0, 0, 1])
+    }
+
+    #[test]
+    fn test_symptomatic_isolation() {
+        let tspd = get_time_steps_per_day();
+        let mut agents = agents();
+        let mut isolation = Isolation::new(policy(), &agents);
+        agents.disease_statuses[0].infect();
+
+        agents.disease_statuses[0].progress_infection(2 * tspd);
+        assert_eq!(isolation.newly_symptomatic(&agents), vec![0]);
+        assert!(isolation.newly_symptomatic(&agents).is_empty());
+        // isolation starts once the delay after the symptoms appearing has passed
+        assert!(isolation.update_day(2, &agents).is_empty());
+        agents.disease_statuses[0].progress_infection(tspd);
+        assert_eq!(isolation.update_day(3, &agents), vec![0]);
+        assert!(isolation.is_staying_home(0));
+        assert_eq!(isolation.num_staying_home(), 1);
+
+        // agents only isolate once for their own symptoms, and stay home for the isolation period
+        agents.disease_statuses[0].progress_infection(tspd);
+        assert!(isolation.update_day(4, &agents).is_empty());
+        isolation.update_day(9, &agents);
+        assert!(isolation.is_staying_home(0));
+        isolation.update_day(10, &agents);
+        assert!(!isolation.is_staying_home(0));
+        assert_eq!(isolation.num_staying_home(), 0);
+    }
+
+    #[test]
+    fn test_household_quarantine() {
+        let mut agents = agents();
+        let mut isolation = Isolation::new(IsolationPolicy { household_quarantine: true, ..policy() }, &agents);
+        agents.disease_statuses[0].infect();
+        agents.disease_statuses[0].progress_infection(3 * get_time_steps_per_day());
+
+        // only the isolating agent's household quarantines, for the quarantine period
+        assert_eq!(isolation.update_day(3, &agents), vec![0, 1]);
+        assert!(!isolation.is_staying_home(2));
+        isolation.update_day(10, &agents);
+        assert!(!isolation.is_staying_home(0) && isolation.is_staying_home(1));
+        // staying home for longer only extends it, rather than starting it again
+        assert!(!isolation.stay_home(1, 20));
+        isolation.update_day(17, &agents);
+        assert!(isolation.is_staying_home(1));
+        isolation.update_day(20, &agents);
+        assert_eq!(isolation.num_staying_home(), 0);
+    }
+
+    #[test]
+    fn test_non_compliant_agents_stay_out() {
+        let mut agents = agents();
+        agents.disease_statuses[0].infect();
+        agents.disease_statuses[0].progress_infection(3 * get_time_steps_per_day());
+
+        let mut isolation = Isolation::new(IsolationPolicy { compliance: 0.0, household_quarantine: true, ..policy() }, &agents);
+        assert_eq!(isolation.newly_symptomatic(&agents), vec![0]);
+        assert!(!isolation.complies(0));
+        assert!(isolation.update_day(3, &agents).is_empty());
+        assert_eq!(isolation.num_staying_home(), 0);
+
+        // agents isolating can still have household members who don't quarantine
+        let mut isolation = Isolation::new(IsolationPolicy { household_compliance: 0.0, household_quarantine: true, ..policy() }, &agents);
+        assert_eq!(isolation.update_day(3, &agents), vec![0]);
+        assert!(!isolation.is_staying_home(1));
+
+        // nor do agents without symptoms isolate
+        let mut isolation = Isolation::new(IsolationPolicy { symptomatic_share: 0.0, ..policy() }, &agents);
+        assert!(isolation.newly_symptomatic(&agents).is_empty());
+        assert!(isolation.update_day(3, &agents).is_empty());
+    }
+}
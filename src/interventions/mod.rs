@@ -0,0 +1,243 @@
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+
+use rand::Rng;
+
+pub use crate::interventions::isolation::{Isolation, IsolationPolicy};
+pub use crate::interventions::policy::{Policy, Scenario, ScheduledPolicy};
+pub use crate::interventions::testing::{Testing, TestingMetric, TestingPolicy, TestType, TracingPolicy};
+pub use crate::interventions::trigger::{Metric, Observation, Trigger};
+use crate::agents::Agents;
+use crate::containers::{Container, Containers, ContainerType};
+use crate::disease::{MaskUsage, MixingStrategy};
+use crate::interventions::trigger::TriggerState;
+use crate::shared::{get_time_steps_per_day, RngStream, seeded_rng};
+use crate::shared::types::TimeStep;
+
+mod isolation;
+mod policy;
+mod testing;
+mod trigger;
+
+/// # Interventions
+/// The policies of a scenario and which of them are in effect on the current day.
+///
+/// Policies that keep agents at home are checked as an agent leaves home for their occupational
+/// container, and policies that change transmission, i.e. mask mandates, are applied to the
+/// containers whenever the set of active policies changes.
+///
+/// Triggered policies are evaluated on the daily history of the agents' disease states and hospital
+/// occupancy, and isolating or quarantining agents, including the traced contacts of those testing
+/// positive, stay at home in the same way as those complying with a lockdown.
+#[derive(Clone, Default)]
+pub struct Interventions {
+    scheduled: Vec<ScheduledPolicy>,
+    active: Vec<bool>,
+    trigger_states: Vec<TriggerState>,
+    /// What was observed of the epidemic at the start of every day so far
+    observations: Vec<Observation>,
+    /// A uniform draw per agent, so the same agents comply with a policy on every day it's active
+    compliance_draws: Vec<f32>,
+    /// Symptomatic isolation and household quarantine, if the scenario has an isolation policy
+    isolation: Option<Isolation>,
+    /// Testing of symptomatic agents and tracing of their contacts, if the scenario has a testing
+    /// policy
+    testing: Option<Testing>,
+}
+
+impl Interventions {
+    pub fn new(scheduled: Vec<ScheduledPolicy>, num_agents: u32) -> Self {
+        let mut rng = seeded_rng(RngStream::Compliance, &[]);
+        Interventions {
+            active: vec![false; scheduled.len()],
+            trigger_states: vec![TriggerState::default(); scheduled.len()],
+            observations: Vec::new(),
+            isolation: None,
+            testing: None,
+            scheduled,
+            compliance_draws: (0..num_agents).map(|_| rng.gen::<f32>()).collect(),
+        }
+    }
+
+    /// Reads the interventions from a JSON scenario file
+    pub fn from_scenario_file<P: AsRef<Path>>(path: P, agents: &Agents) -> Result<Self, Box<dyn Error>> {
+        let scenario: Scenario = serde_json::from_reader(File::open(path)?)?;
+        if scenario.testing.is_some() && scenario.isolation.is_none() {
+            return Err("a scenario with testing needs an isolation policy for those who test positive".into());
+        }
+        let mut interventions = Interventions::new(scenario.interventions, agents.num_agents);
+        interventions.isolation = scenario.isolation.map(|policy| Isolation::new(policy, agents));
+        interventions.testing = scenario.testing.map(|policy| Testing::new(policy, agents.num_agents));
+        Ok(interventions)
+    }
+
+    #[inline]
+    pub fn scheduled(&self) -> &[ScheduledPolicy] {
+        &self.scheduled
+    }
+
+    #[inline]
+    pub fn active_policies(&self) -> impl Iterator<Item=&Policy> {
+        self.scheduled.iter().zip(self.active.iter())
+            .filter(|(_, &active)| active)
+            .map(|(scheduled, _)| &scheduled.policy)
+    }
+
+    #[inline]
+    pub fn isolation(&self) -> Option<&Isolation> {
+        self.isolation.as_ref()
+    }
+
+    #[inline]
+    pub fn isolation_mut(&mut self) -> Option<&mut Isolation> {
+        self.isolation.as_mut()
+    }
+
+    #[inline]
+    pub fn testing(&self) -> Option<&Testing> {
+        self.testing.as_ref()
+    }
+
+    /// How many time steps of visits to containers contact tracing needs, if any
+    #[inline]
+    pub fn contact_lookback_time_steps(&self) -> Option<TimeStep> {
+        self.testing.as_ref()
+            .and_then(|testing| testing.policy().tracing.as_ref())
+            .map(|tracing| tracing.lookback_days * get_time_steps_per_day())
+    }
+
+    /// Starts and ends isolation and quarantine from symptoms, testing, and contact tracing,
+    /// returning the agents who've started staying at home.
+    ///
+    /// The agents' disease statuses should be up to date as of the start of the day.
+    pub fn update_staying_home<M>(&mut self, day: u32, time_step: TimeStep, agents: &mut Agents, containers: &Containers<M>) -> Vec<u32>
+        where M: MixingStrategy
+    {
+        let isolation = match self.isolation.as_mut() {
+            Some(isolation) => { isolation }
+            None => { return Vec::new(); }
+        };
+        let newly_symptomatic = isolation.newly_symptomatic(agents);
+        let mut started = isolation.update_day(day, agents);
+
+        if let Some(testing) = self.testing.as_mut() {
+            let outcome = testing.update_day(day, agents, &newly_symptomatic);
+            for agent_idx in outcome.positives {
+                if isolation.complies(agent_idx) {
+                    isolation.isolate(agent_idx, day, &mut started);
+                }
+            }
+
+            if let (Some(tracing), Some(history)) = (testing.policy().tracing.clone(), agents.locations.contact_history()) {
+                let mut rng = seeded_rng(RngStream::Tracing, &[day as u64]);
+                let since_time_step = time_step.saturating_sub(tracing.lookback_days * get_time_steps_per_day());
+                for index_case in outcome.to_trace {
+                    let traced: Vec<u32> = history.contacts(index_case, since_time_step, time_step, containers).into_iter()
+                        .filter(|_| rng.gen::<f32>() < tracing.coverage)
+                        .collect();
+                    testing.record_traced(traced.len() as u32);
+                    for contact_idx in traced {
+                        if isolation.stay_home(contact_idx, day + tracing.quarantine_days) {
+                            started.push(contact_idx);
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(history) = agents.locations.contact_history_mut() {
+            history.prune(time_step);
+        }
+        started
+    }
+
+    /// The number of agents isolating or quarantining at home
+    #[inline]
+    pub fn num_staying_home(&self) -> usize {
+        self.isolation.as_ref().map_or(0, |isolation| isolation.num_staying_home())
+    }
+
+    /// The names of the active policies separated by semicolons, for use in reports
+    pub fn describe_active(&self) -> String {
+        self.active_policies().map(|policy| policy.name()).collect::<Vec<&str>>().join(";")
+    }
+
+    /// Whether any of the policies are switched on and off by a trigger
+    #[inline]
+    pub fn has_triggers(&self) -> bool {
+        self.scheduled.iter().any(|scheduled| scheduled.trigger.is_some())
+    }
+
+    /// What would have been reported of the epidemic on the given day, given a reporting delay
+    #[inline]
+    fn observed(&self, day: u32, reporting_delay_days: u32) -> Option<&Observation> {
+        day.checked_sub(reporting_delay_days).and_then(|reported_day| self.observations.get(reported_day as usize))
+    }
+
+    /// Updates which policies are in effect on the given day from what's observed of the epidemic
+    /// at the start of it, returning whether any changed.
+    ///
+    /// Expects to be called once for every day in order, `observation` only being needed if any
+    /// policy has a trigger.
+    pub fn update_day(&mut self, day: u32, observation: Option<Observation>) -> bool {
+        if let Some(observation) = observation {
+            self.observations.push(observation);
+        }
+
+        let mut changed = false;
+        for idx in 0..self.scheduled.len() {
+            let scheduled = &self.scheduled[idx];
+            let is_active = match &scheduled.trigger {
+                None => { scheduled.is_active(day) }
+                Some(trigger) if scheduled.is_active(day) => {
+                    let observed = self.observed(day, trigger.reporting_delay_days)
+                        .map(|observation| trigger.metric.value(observation));
+                    self.trigger_states[idx].update(trigger, day, observed)
+                }
+                Some(_) => {
+                    self.trigger_states[idx].reset(day);
+                    false
+                }
+            };
+            changed |= is_active != self.active[idx];
+            self.active[idx] = is_active;
+        }
+        changed
+    }
+
+    /// Sets the mask usage within every container from the active policies
+    pub fn apply_to_containers<M: MixingStrategy>(&self, containers: &mut Containers<M>) {
+        for container in containers.iter_mut() {
+            container.mask_usage = self.mask_usage(container.container_type);
+        }
+    }
+
+    /// The masks required within a container of the given type, the most stringent of each
+    /// overlapping mandate's values applying
+    #[inline]
+    pub fn mask_usage(&self, container_type: ContainerType) -> Option<MaskUsage> {
+        self.active_policies().filter_map(|policy| policy.mask_usage(container_type)).reduce(MaskUsage::max)
+    }
+
+    /// Whether an agent should stay at home rather than leave for the given occupational container
+    pub fn stays_home<M: MixingStrategy>(&self, agent_idx: u32, occupational_container: &Container<M>) -> bool {
+        let draw = self.compliance_draws.get(agent_idx as usize).copied().unwrap_or(1.0);
+        let container_type = occupational_container.container_type;
+
+        if self.isolation.as_ref().is_some_and(|isolation| isolation.is_staying_home(agent_idx)) {
+            return true;
+        }
+        self.active_policies().any(|policy| {
+            match policy {
+                Policy::Lockdown { compliance } => { draw < *compliance }
+                Policy::WorkFromHome { share } => { container_type == ContainerType::Workplace && draw < *share }
+                Policy::Closure { container_types } => { container_types.contains(&container_type) }
+                Policy::CapacityLimit { container_types, max_occupancy } => {
+                    container_types.contains(&container_type) && occupational_container.inhabitants.len() as u32 >= *max_occupancy
+                }
+                Policy::MaskMandate { .. } => { false }
+            }
+        })
+    }
+}
@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+
+use crate::containers::ContainerType;
+use crate::disease::MaskUsage;
+use crate::interventions::isolation::IsolationPolicy;
+use crate::interventions::testing::TestingPolicy;
+use crate::interventions::trigger::Trigger;
+
+/// A non-pharmaceutical intervention changing how agents behave or how the disease transmits
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Policy {
+    /// Agents stay at home rather than going to their occupational container, `compliance` being
+    /// the share of agents who follow it
+    Lockdown { compliance: f32 },
+    /// A share of the agents who work in a workplace (rather than attend a school) stay at home
+    WorkFromHome { share: f32 },
+    /// Containers of the given types are closed and those assigned to them stay at home, e.g.
+    /// school closures
+    Closure { container_types: Vec<ContainerType> },
+    /// Agents stay at home rather than go to a container of the given types once it holds
+    /// `max_occupancy` agents
+    CapacityLimit { container_types: Vec<ContainerType>, max_occupancy: u32 },
+    /// Masks are required within containers of the given types, worn by the share of agents given
+    /// by `compliance` and reducing transmission from and to those wearing them.
+    ///
+    /// Scenarios from before masks were worn by individual agents gave a `transmission_reduction`
+    /// instead, which is read as the `source_control` of masks worn by everyone, having the same
+    /// effect.
+    MaskMandate {
+        container_types: Vec<ContainerType>,
+        #[serde(default = "default_mask_compliance")]
+        compliance: f32,
+        #[serde(alias = "transmission_reduction")]
+        source_control: f32,
+        #[serde(default)]
+        wearer_protection: f32,
+    },
+}
+
+fn default_mask_compliance() -> f32 { 1.0 }
+
+impl Policy {
+    /// A short name used to identify the policy in reports
+    pub fn name(&self) -> &'static str {
+        match self {
+            Policy::Lockdown { .. } => { "lockdown" }
+            Policy::WorkFromHome { .. } => { "work_from_home" }
+            Policy::Closure { .. } => { "closure" }
+            Policy::CapacityLimit { .. } => { "capacity_limit" }
+            Policy::MaskMandate { .. } => { "mask_mandate" }
+        }
+    }
+
+    /// The masks the policy requires within a container of the given type
+    #[inline]
+    pub fn mask_usage(&self, container_type: ContainerType) -> Option<MaskUsage> {
+        match self {
+            Policy::MaskMandate { container_types, compliance, source_control, wearer_protection }
+            if container_types.contains(&container_type) => {
+                Some(MaskUsage { compliance: *compliance, source_control: *source_control, wearer_protection: *wearer_protection })
+            }
+            _ => { None }
+        }
+    }
+}
+
+/// A policy along with the days it's in effect
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ScheduledPolicy {
+    pub policy: Policy,
+    /// The first day the policy is in effect
+    #[serde(default)]
+    pub start_day: u32,
+    /// The day the policy is lifted, or `None` for it to last until the end of the simulation
+    #[serde(default)]
+    pub end_day: Option<u32>,
+    /// Switches the policy on and off from observed metrics, in which case the policy is only in
+    /// effect while both triggered and between its start and end days
+    #[serde(default)]
+    pub trigger: Option<Trigger>,
+}
+
+impl ScheduledPolicy {
+    /// Whether the day is between the policy's start and end days
+    #[inline]
+    pub fn is_active(&self, day: u32) -> bool {
+        day >= self.start_day && self.end_day.is_none_or(|end_day| day < end_day)
+    }
+}
+
+/// The contents of a scenario file
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Scenario {
+    #[serde(default)]
+    pub interventions: Vec<ScheduledPolicy>,
+    #[serde(default)]
+    pub isolation: Option<IsolationPolicy>,
+    /// Testing of symptomatic agents, those testing positive isolating as described by `isolation`
+    #[serde(default)]
+    pub testing: Option<TestingPolicy>,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interventions::trigger::Metric;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_scenario() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "interventions": [
+                {"policy": {"type": "lockdown", "compliance": 0.8}, "start_day": 10, "end_day": 30},
+                {"policy": {"type": "closure", "container_types": ["school"]}, "start_day": 5}
+            ]
+        }"#).unwrap();
+
+        assert_eq!(scenario.interventions[0].policy, Policy::Lockdown { compliance: 0.8 });
+        assert!(!scenario.interventions[0].is_active(9));
+        assert!(scenario.interventions[0].is_active(10));
+        assert!(!scenario.interventions[0].is_active(30));
+        assert_eq!(scenario.interventions[1].policy, Policy::Closure { container_types: vec![ContainerType::School] });
+        assert!(scenario.interventions[1].is_active(1000));
+        assert_eq!(scenario.interventions[1].trigger, None);
+    }
+
+    #[test]
+    fn test_parse_mask_mandate() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "interventions": [
+                {"policy": {"type": "mask_mandate", "container_types": ["workplace", "school"],
+                            "compliance": 0.8, "source_control": 0.5, "wearer_protection": 0.3}, "start_day": 10}
+            ]
+        }"#).unwrap();
+
+        let policy = &scenario.interventions[0].policy;
+        assert_eq!(policy.mask_usage(ContainerType::School),
+                   Some(MaskUsage { compliance: 0.8, source_control: 0.5, wearer_protection: 0.3 }));
+        assert_eq!(policy.mask_usage(ContainerType::Household), None);
+
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "interventions": [{"policy": {"type": "mask_mandate", "container_types": ["workplace"], "transmission_reduction": 0.4}}]
+        }"#).unwrap();
+        assert_eq!(scenario.interventions[0].policy.mask_usage(ContainerType::Workplace),
+                   Some(MaskUsage { compliance: 1.0, source_control: 0.4, wearer_protection: 0.0 }));
+    }
+
+    #[test]
+    fn test_parse_triggered_policy() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "interventions": [
+                {
+                    "policy": {"type": "lockdown", "compliance": 0.9},
+                    "trigger": {"metric": "infectious_prevalence", "on_threshold": 0.02, "off_threshold": 0.005,
+                                "reporting_delay_days": 3, "min_active_days": 14}
+                }
+            ]
+        }"#).unwrap();
+
+        let scheduled = &scenario.interventions[0];
+        assert_eq!(scheduled.start_day, 0);
+        let trigger = scheduled.trigger.as_ref().unwrap();
+        assert_eq!(trigger.metric, Metric::InfectiousPrevalence);
+        assert_eq!(trigger.reporting_delay_days, 3);
+        assert_eq!(trigger.min_inactive_days, 0);
+    }
+
+    #[test]
+    fn test_parse_isolation() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "isolation": {"symptomatic_share": 0.6, "symptom_onset_days": 5.0, "compliance": 0.7, "isolation_days": 10,
+                          "household_quarantine": true, "quarantine_days": 14}
+        }"#).unwrap();
+
+        assert!(scenario.interventions.is_empty());
+        let isolation = scenario.isolation.unwrap();
+        assert_eq!(isolation.delay_days, 0.0);
+        assert_eq!(isolation.household_compliance, 1.0);
+        assert!(isolation.household_quarantine);
+        assert!(scenario.testing.is_none());
+    }
+
+    #[test]
+    fn test_parse_testing() {
+        let scenario: Scenario = serde_json::from_str(r#"{
+            "isolation": {"symptomatic_share": 0.6, "symptom_onset_days": 5.0, "compliance": 0.7, "isolation_days": 10},
+            "testing": {
+                "daily_capacity": 1000,
+                "test_type": {"sensitivity": 0.85, "specificity": 0.99, "turnaround_days": 2},
+                "test_seeking": 0.5,
+                "tracing": {"coverage": 0.6, "delay_days": 1, "lookback_days": 7, "quarantine_days": 14}
+            }
+        }"#).unwrap();
+
+        let testing = scenario.testing.unwrap();
+        assert_eq!(testing.background_test_rate, 0.0);
+        assert_eq!(testing.test_type.turnaround_days, 2);
+        assert_eq!(testing.tracing.unwrap().lookback_days, 7);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::agents::Agents;
+use crate::disease::State;
+use crate::shared::{RngStream, seeded_rng};
+
+/// How accurate a test is and how long its results take
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestType {
+    /// The chance an infected agent tests positive
+    pub sensitivity: f32,
+    /// The chance an uninfected agent tests negative
+    pub specificity: f32,
+    #[serde(default)]
+    pub turnaround_days: u32,
+}
+
+/// Tracing the contacts of those who test positive, from the containers they've shared recently
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TracingPolicy {
+    /// The share of contacts who are successfully traced
+    pub coverage: f32,
+    /// Days from a positive result to the contacts being traced
+    #[serde(default)]
+    pub delay_days: u32,
+    /// How many days before being traced a contact is counted
+    pub lookback_days: u32,
+    pub quarantine_days: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TestingPolicy {
+    /// The number of tests that can be performed each day, those seeking a test beyond it waiting
+    /// until the next day
+    pub daily_capacity: u32,
+    pub test_type: TestType,
+    /// The share of symptomatic agents who seek a test
+    pub test_seeking: f32,
+    /// The daily chance an agent who isn't infected seeks a test, e.g. from having symptoms of
+    /// something else
+    #[serde(default)]
+    pub background_test_rate: f32,
+    #[serde(default)]
+    pub tracing: Option<TracingPolicy>,
+}
+
+/// The testing and tracing done on a day, as written to the testing report
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct TestingMetric {
+    pub day: u32,
+    pub tests_performed: u32,
+    pub positives: u32,
+    pub contacts_traced: u32,
+    /// Those still waiting for a test at the end of the day
+    pub queue_length: usize,
+}
+
+/// The results of a day's testing that need acting on
+#[derive(Default)]
+pub struct TestingOutcome {
+    /// The agents whose positive results have come back
+    pub positives: Vec<u32>,
+    /// The positive cases whose contacts are due to be traced
+    pub to_trace: Vec<u32>,
+}
+
+/// # Testing
+/// The queue of agents waiting for a test, and the results and traces pending.
+#[derive(Clone)]
+pub struct Testing {
+    policy: TestingPolicy,
+    queue: VecDeque<u32>,
+    /// Whether each agent is waiting for a test or its result
+    pending: Vec<bool>,
+    /// The day each result comes back, the agent tested, and whether it's positive, in the order
+    /// they come back
+    results: VecDeque<(u32, u32, bool)>,
+    /// The day the contacts of each positive case are traced, in the order they're traced
+    traces: VecDeque<(u32, u32)>,
+    metric: TestingMetric,
+}
+
+impl Testing {
+    pub fn new(policy: TestingPolicy, num_agents: u32) -> Self {
+        Testing {
+            policy,
+            queue: VecDeque::new(),
+            pending: vec![false; num_agents as usize],
+            results: VecDeque::new(),
+            traces: VecDeque::new(),
+            metric: TestingMetric::default(),
+        }
+    }
+
+    #[inline]
+    pub fn policy(&self) -> &TestingPolicy {
+        &self.policy
+    }
+
+    /// The testing and tracing done on the most recent day
+    #[inline]
+    pub fn metric(&self) -> TestingMetric {
+        self.metric
+    }
+
+    #[inline]
+    pub fn record_traced(&mut self, num_traced: u32) {
+        self.metric.contacts_traced += num_traced;
+    }
+
+    /// Queues those seeking a test, performs as many tests as there's capacity for, and returns
+    /// the results and traces due on the given day
+    pub fn update_day(&mut self, day: u32, agents: &Agents, newly_symptomatic: &[u32]) -> TestingOutcome {
+        let mut rng = seeded_rng(RngStream::Testing, &[day as u64]);
+        self.metric = TestingMetric { day, ..TestingMetric::default() };
+
+        for &agent_idx in newly_symptomatic {
+            if rng.gen::<f32>() < self.policy.test_seeking {
+                self.seek_test(agent_idx);
+            }
+        }
+        if self.policy.background_test_rate > 0.0 {
+            for agent_idx in 0..agents.num_agents {
+                let status = &agents.disease_statuses[agent_idx as usize];
+                if !status.is_infected() && status.state != State::Dead && rng.gen::<f32>() < self.policy.background_test_rate {
+                    self.seek_test(agent_idx);
+                }
+            }
+        }
+
+        let num_tests = (self.policy.daily_capacity as usize).min(self.queue.len());
+        for agent_idx in self.queue.drain(..num_tests) {
+            let probability_positive = match agents.disease_statuses[agent_idx as usize].is_infected() {
+                true => { self.policy.test_type.sensitivity }
+                false => { 1.0 - self.policy.test_type.specificity }
+            };
+            self.results.push_back((day + self.policy.test_type.turnaround_days, agent_idx, rng.gen::<f32>() < probability_positive));
+        }
+        self.metric.tests_performed = num_tests as u32;
+        self.metric.queue_length = self.queue.len();
+
+        let mut outcome = TestingOutcome::default();
+        while self.results.front().is_some_and(|&(result_day, _, _)| result_day <= day) {
+            let (_, agent_idx, positive) = self.results.pop_front().unwrap();
+            self.pending[agent_idx as usize] = false;
+            if positive {
+                outcome.positives.push(agent_idx);
+                if let Some(tracing) = &self.policy.tracing {
+                    self.traces.push_back((day + tracing.delay_days, agent_idx));
+                }
+            }
+        }
+        self.metric.positives = outcome.positives.len() as u32;
+
+        while self.traces.front().is_some_and(|&(trace_day, _)| trace_day <= day) {
+            outcome.to_trace.push(self.traces.pop_front().unwrap().1);
+        }
+        outcome
+    }
+
+    #[inline]
+    fn seek_test(&mut self, agent_idx: u32) {
+        if !self.pending[agent_idx as usize] {
+            self.pending[agent_idx as usize] = true;
+            self.queue.push_back(agent_idx);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::agents::Location;
+    use crate::containers::Containers;
+    use crate::disease::Uniform;
+    use crate::interventions::{Interventions, Isolation, IsolationPolicy};
+    use crate::shared::{get_time_steps_per_day, GLOBAL_PARAMS, GlobalSimParams};
+    use crate::Vec2;
+
+    use super::*;
+
+    fn policy() -> TestingPolicy {
+        TestingPolicy {
+            daily_capacity: 2,
+            test_type: TestType { sensitivity: 1.0, specificity: 1.0, turnaround_days: 2 },
+            test_seeking: 1.0,
+            background_test_rate: 0.0,
+            tracing: Some(TracingPolicy { coverage: 1.0, delay_days: 1, lookback_days: 2, quarantine_days: 5 }),
+        }
+    }
+
+    #[test]
+    fn test_capacity_and_turnaround() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let mut agents = Agents::with_households(vec![30; 4], vec![0, 1, 2, 3]);
+        for agent_idx in 0..3 {
+            agents.disease_statuses[agent_idx].infect();
+        }
+        let mut testing = Testing::new(policy(), agents.num_agents);
+
+        // those beyond the day's capacity wait for the next day
+        assert!(testing.update_day(0, &agents, &[0, 1, 2, 3]).positives.is_empty());
+        assert_eq!((testing.metric().tests_performed, testing.metric().queue_length), (2, 2));
+        // agents waiting for a test or its result aren't queued again
+        assert!(testing.update_day(1, &agents, &[0]).positives.is_empty());
+        assert_eq!((testing.metric().tests_performed, testing.metric().queue_length), (2, 0));
+
+        // results come back after the turnaround, and contacts are traced after the tracing delay
+        let outcome = testing.update_day(2, &agents, &[]);
+        assert_eq!((outcome.positives, outcome.to_trace), (vec![0, 1], vec![]));
+        assert_eq!(testing.metric().positives, 2);
+        let outcome = testing.update_day(3, &agents, &[]);
+        assert_eq!((outcome.positives, outcome.to_trace), (vec![2], vec![0, 1]));
+        assert_eq!(testing.update_day(4, &agents, &[]).to_trace, vec![2]);
+        assert_eq!(testing.metric().tests_performed, 0);
+    }
+
+    #[test]
+    fn test_positives_isolate_and_contacts_quarantine() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let tspd = get_time_steps_per_day();
+        // the first two agents share a household, and the first and third a workplace
+        let household_positions = [Vec2::new(0.0, 0.0), Vec2::new(1.0, 0.0), Vec2::new(2.0, 0.0)];
+        let mut containers = Containers::new(&household_positions, &[Vec2::new(0.0, 1.0)], Uniform { transmission_chance: 0.0 });
+        let workplace_idx = containers.get_workplace_idx(0);
+        let mut agents = Agents::with_households(vec![30; 4], vec![0, 0, 1, 2]);
+        for (agent_idx, &household_idx) in agents.household_container.iter().enumerate() {
+            containers.push_inhabitant_no_update(household_idx, agent_idx as u32);
+        }
+
+        // symptoms alone don't lead to isolating within the test, only testing positive does
+        let isolation = IsolationPolicy {
+            symptomatic_share: 1.0,
+            symptom_onset_days: 1.0,
+            compliance: 1.0,
+            delay_days: 10.0,
+            isolation_days: 7,
+            household_quarantine: true,
+            household_compliance: 1.0,
+            quarantine_days: 7,
+        };
+        let testing = TestingPolicy {
+            test_type: TestType { turnaround_days: 1, ..policy().test_type },
+            tracing: Some(TracingPolicy { delay_days: 0, ..policy().tracing.unwrap() }),
+            ..policy()
+        };
+        let mut interventions = Interventions::new(Vec::new(), agents.num_agents);
+        interventions.isolation = Some(Isolation::new(isolation, &agents));
+        interventions.testing = Some(Testing::new(testing, agents.num_agents));
+        agents.locations.record_contacts(interventions.contact_lookback_time_steps(), 0);
+
+        for (agent_idx, household_idx) in [(0, 0), (2, 1)] {
+            containers.remove_inhabitant(household_idx, agent_idx, 10, &mut agents);
+            containers.push_inhabitant(workplace_idx, agent_idx, 10, &mut agents);
+            agents.locations.set(agent_idx, Location::InContainer(workplace_idx), 10);
+        }
+        for (agent_idx, household_idx) in [(0, 0), (2, 1)] {
+            containers.remove_inhabitant(workplace_idx, agent_idx, 20, &mut agents);
+            containers.push_inhabitant(household_idx, agent_idx, 20, &mut agents);
+            agents.locations.set(agent_idx, Location::InContainer(household_idx), 20);
+        }
+        agents.disease_statuses[0].infect();
+        agents.disease_statuses[0].progress_infection(tspd);
+
+        assert!(interventions.update_staying_home(1, tspd, &mut agents, &containers).is_empty());
+        // the positive case isolates along with their household, and their workplace contact quarantines
+        assert_eq!(interventions.update_staying_home(2, 2 * tspd, &mut agents, &containers), vec![0, 1, 2]);
+        assert_eq!(interventions.testing().unwrap().metric().contacts_traced, 2);
+        assert_eq!(interventions.num_staying_home(), 3);
+        assert!(!interventions.isolation().unwrap().is_staying_home(3));
+
+        // traced contacts only stay home for the tracing policy's quarantine
+        assert!(interventions.update_staying_home(7, 7 * tspd, &mut agents, &containers).is_empty());
+        assert_eq!(interventions.num_staying_home(), 2);
+        assert!(!interventions.isolation().unwrap().is_staying_home(2));
+    }
+}
@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+
+use crate::reporting::StateCounts;
+
+/// An observed quantity of the epidemic that a policy can be triggered on
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    /// The share of the population that's infectious
+    InfectiousPrevalence,
+    /// The share of the population that's exposed or infectious
+    InfectedPrevalence,
+    /// The share of hospital beds in use, including agents admitted without a bed, so it can
+    /// exceed 1. Always 0 without hospitalisation.
+    HospitalOccupancy,
+    /// The number of agents in hospital, always 0 without hospitalisation
+    Hospitalised,
+}
+
+impl Metric {
+    #[inline]
+    pub fn value(&self, observation: &Observation) -> f32 {
+        let counts = &observation.state_counts;
+        let total = counts.total().max(1) as f32;
+        match self {
+            Metric::InfectiousPrevalence => { counts.num_infectious as f32 / total }
+            Metric::InfectedPrevalence => { (counts.num_exposed + counts.num_infectious) as f32 / total }
+            Metric::HospitalOccupancy => { observation.num_hospitalised as f32 / observation.num_beds.max(1) as f32 }
+            Metric::Hospitalised => { observation.num_hospitalised as f32 }
+        }
+    }
+}
+
+/// What's known of the epidemic at the start of a day, that triggers are evaluated on
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Observation {
+    pub state_counts: StateCounts,
+    pub num_hospitalised: usize,
+    /// The general and ICU beds across every hospital
+    pub num_beds: usize,
+}
+
+/// Switches a policy on once a metric rises to `on_threshold` and off once it falls below
+/// `off_threshold`, where the gap between the two thresholds gives the switching hysteresis.
+///
+/// The metric is observed `reporting_delay_days` after the fact, and once switched the policy stays
+/// in that state for at least `min_active_days` (or `min_inactive_days`) before it can switch back.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Trigger {
+    pub metric: Metric,
+    pub on_threshold: f32,
+    pub off_threshold: f32,
+    #[serde(default)]
+    pub reporting_delay_days: u32,
+    #[serde(default)]
+    pub min_active_days: u32,
+    #[serde(default)]
+    pub min_inactive_days: u32,
+}
+
+/// Whether a triggered policy is switched on, and since when
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TriggerState {
+    on: bool,
+    since_day: u32,
+}
+
+impl TriggerState {
+    /// Switches off without waiting for the minimum duration, e.g. when a policy's window ends
+    #[inline]
+    pub fn reset(&mut self, day: u32) {
+        if self.on {
+            *self = TriggerState { on: false, since_day: day };
+        }
+    }
+
+    /// Updates the state from the metric observed on the given day, `None` if nothing has been
+    /// reported yet, returning whether the policy is switched on
+    pub fn update(&mut self, trigger: &Trigger, day: u32, observed: Option<f32>) -> bool {
+        let days_in_state = day.saturating_sub(self.since_day);
+        if let Some(value) = observed {
+            let switch = if self.on {
+                days_in_state >= trigger.min_active_days && value < trigger.off_threshold
+            } else {
+                days_in_state >= trigger.min_inactive_days && value >= trigger.on_threshold
+            };
+            if switch {
+                *self = TriggerState { on: !self.on, since_day: day };
+            }
+        }
+        self.on
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trigger_hysteresis_and_min_duration() {
+        let trigger = Trigger {
+            metric: Metric::InfectiousPrevalence,
+            on_threshold: 0.1,
+            off_threshold: 0.05,
+            reporting_delay_days: 0,
+            min_active_days: 3,
+            min_inactive_days: 0,
+        };
+        let mut state = TriggerState::default();
+
+        assert!(!state.update(&trigger, 0, None));
+        assert!(!state.update(&trigger, 1, Some(0.09)));
+        assert!(state.update(&trigger, 2, Some(0.1)));
+        // below the on threshold but above the off threshold, so stays on
+        assert!(state.update(&trigger, 3, Some(0.07)));
+        // below the off threshold but not yet on for the minimum duration
+        assert!(state.update(&trigger, 4, Some(0.01)));
+        assert!(!state.update(&trigger, 5, Some(0.01)));
+        assert!(!state.update(&trigger, 6, Some(0.07)));
+    }
+
+    #[test]
+    fn test_metric_values() {
+        let observation = Observation {
+            state_counts: StateCounts { num_susceptible: 6, num_exposed: 1, num_infectious: 2, num_recovered: 1, num_dead: 0 },
+            num_hospitalised: 5,
+            num_beds: 4,
+        };
+        assert_eq!(Metric::InfectiousPrevalence.value(&observation), 0.2);
+        assert_eq!(Metric::InfectedPrevalence.value(&observation), 0.3);
+        assert_eq!(Metric::HospitalOccupancy.value(&observation), 1.25);
+        assert_eq!(Metric::Hospitalised.value(&observation), 5.0);
+
+        let without_hospitals = Observation { state_counts: observation.state_counts, ..Observation::default() };
+        assert_eq!(Metric::HospitalOccupancy.value(&without_hospitals), 0.0);
+        assert_eq!(Metric::Hospitalised.value(&without_hospitals), 0.0);
+    }
+}
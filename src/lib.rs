@@ -1,7 +1,9 @@
-use std::fmt;
+use std::{fmt, iter};
+use std::error::Error;
 use std::path::PathBuf;
 
 use fast_paths::FastGraph;
+use nonmax::NonMaxU64;
 
 pub use flatbuffer::Bounds;
 pub use flatbuffer::root_as_model;
@@ -9,16 +11,20 @@ pub use flatbuffer::Model;
 pub use flatbuffer::read_buffer;
 pub use flatbuffer::TransitGraph;
 pub use flatbuffer::Vec2;
-use routing::transit::get_fast_graph;
 use shared::types::TimeStep;
 
-use crate::agents::Agents;
-use crate::containers::Containers;
-use crate::disease::{MixingStrategy, Uniform};
-use crate::events::Events;
-use crate::routing::{GranularGrid, nodes_to_granular_grid};
-use crate::shared::{GlobalSimParams, set_up_global_params, get_time_steps_per_day, get_simulation_length_in_days};
-use crate::routing::transit::{load_fast_graph_from_disk, save_fast_graph_to_disk};
+use crate::agents::{Agents, Location};
+use crate::containers::{Containers, ContainerType};
+use crate::disease::{DiseaseStatus, Hospital, Hospitalisation, HospitalisationParams, HospitalUpdates, Importation, ImportationParams,
+                     InfectionSource, MixingStrategy, Seeding, SeedingParams, Transmission, Uniform};
+use crate::events::{Event, EventHandle, Events, EventType, TravelType};
+use crate::interventions::{Interventions, Observation};
+use crate::reporting::{AdminRegions, container_snapshots, ContainerSnapshot, ObserverInterval, Observers, SimObserver, StateCounts};
+use crate::routing::{DirectRoutingType, GranularGrid, interpolate_along_path, KdTree, nodes_to_granular_grid, Router, RoutingType, SpatialIndex};
+use crate::routing::commute::CommuteCache;
+use crate::routing::congestion::Congestion;
+use crate::shared::{GlobalSimParams, RngStream, seeded_rng, set_up_global_params, get_time_steps_per_day, get_simulation_length_in_days};
+use crate::routing::transit::FastGraphCache;
 
 // TODO Revisit public access
 pub mod agents;
@@ -28,14 +34,35 @@ pub mod shared;
 pub mod routing;
 pub mod events;
 pub mod reporting;
+pub mod interventions;
 mod flatbuffer;
 
-#[derive(Debug, Clone)]
-pub struct EndOfSimulationError;
+/// Why the simulation couldn't carry on
+#[derive(Debug)]
+pub enum SimError {
+    /// The simulation has reached its maximum length
+    EndOfSimulation,
+    /// An observer failed, e.g. to write its report
+    Observer(Box<dyn Error>),
+}
 
-impl fmt::Display for EndOfSimulationError {
+impl fmt::Display for SimError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "simulation has reached the maximum {} days", get_time_steps_per_day())
+        match self {
+            SimError::EndOfSimulation => {
+                write!(f, "simulation has reached the maximum {} days", get_simulation_length_in_days().unwrap_or_default())
+            }
+            SimError::Observer(e) => { write!(f, "an observer failed: {}", e) }
+        }
+    }
+}
+
+impl Error for SimError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SimError::EndOfSimulation => { None }
+            SimError::Observer(e) => { Some(e.as_ref()) }
+        }
     }
 }
 
@@ -47,18 +74,36 @@ pub struct Sim<M: MixingStrategy> {
     pub bounds: Bounds,
     pub fast_graph: FastGraph,
     pub transit_granular_grid: GranularGrid<usize>,
+    pub transit_node_positions: Vec<Vec2>,
+    pub transit_stop_index: KdTree<usize>,
+    pub commute_cache: CommuteCache,
+    pub congestion: Congestion,
+    pub interventions: Interventions,
+    pub hospitalisation: Option<Hospitalisation>,
+    pub importation: Option<Importation>,
+    pub seeding: Option<Seeding>,
+    /// The administrative regions of the households, if the model has them
+    pub regions: Option<AdminRegions>,
+    observers: Observers<M>,
+    /// The routines of agents sent home, to be re-anchored to the next morning once they arrive
+    returning_home: Vec<EventHandle>,
 }
 
 impl Sim<Uniform> {
     // TODO Builder pattern for input params?
-    fn new<P>(synthetic_environment_dir: P, model_name: &str, load_cached_fast_graph: bool, global_params: GlobalSimParams) -> Self
+    fn new<P>(synthetic_environment_dir: P, model_name: &str, load_cached_fast_graph: bool, fast_graph_cache: FastGraphCache,
+              global_params: GlobalSimParams, hospitalisation_params: Option<HospitalisationParams>,
+              importation_params: Option<ImportationParams>) -> Result<Self, Box<dyn Error>>
         where P: Into<PathBuf>
     {
-        set_up_global_params(global_params);
         let mut synthetic_environment_file = synthetic_environment_dir.into().join(model_name);
         synthetic_environment_file.set_extension("txt");
-        let bytes = read_buffer(synthetic_environment_file.as_path());
-        let model = root_as_model(&bytes).unwrap();
+        let bytes = read_buffer(synthetic_environment_file.as_path())?;
+        // checked before setting the global parameters, which can only be set once
+        let model = root_as_model(&bytes)?;
+
+        let congestion = Congestion::new(global_params.road_capacity, global_params.congestion_alpha, global_params.congestion_beta);
+        set_up_global_params(global_params);
 
         let transmission_chance = 0.00005 * 24.0 / get_time_steps_per_day() as f32;
         let mixing_strategy = Uniform { transmission_chance };
@@ -67,45 +112,356 @@ impl Sim<Uniform> {
 
         let mut containers = Containers::<Uniform>::new(model.households().pos(), model.workplaces().pos(), mixing_strategy);
         let mut agents = agents::Agents::new(&model, &mut containers);
-        let events = events::Events::new(&mut agents);
-
-        let fast_graph = match load_cached_fast_graph {
-            true => {
-                match load_fast_graph_from_disk(&*("fast_paths/".to_string() + model_name + ".fp")) {
-                    Ok(graph) => { graph }
-                    Err(e) => {
-                        eprintln!("fast graph couldn't be read from disk");
-                        panic!("{:?}", e);
+        let hospitalisation = hospitalisation_params.map(|params| -> Result<Hospitalisation, Box<dyn Error>> {
+            let hospital_mixing_strategy = Uniform { transmission_chance: transmission_chance * params.hospital_transmission_scale };
+            // models without any hospitals get a single one in the middle
+            let hospital_beds: Vec<(Vec2, u32, u32)> = match model.hospitals() {
+                Some(hospitals) if !hospitals.pos().is_empty() => {
+                    let num_hospitals = hospitals.pos().len();
+                    if hospitals.general_beds().len() != num_hospitals || hospitals.icu_beds().len() != num_hospitals {
+                        return Err(format!("the model has {} hospitals but {} general and {} ICU bed counts", num_hospitals,
+                                           hospitals.general_beds().len(), hospitals.icu_beds().len()).into());
                     }
+                    hospitals.pos().iter().zip(hospitals.general_beds().iter().zip(hospitals.icu_beds().iter()))
+                        .map(|(&pos, (general_beds, icu_beds))| (pos, general_beds, icu_beds))
+                        .collect()
                 }
-            }
-            false => {
-                let fast_graph = get_fast_graph(&model.transit_graph());
-                save_fast_graph_to_disk(&fast_graph, &*("fast_paths/".to_string() + model_name + ".fp")).unwrap();
-                fast_graph
-            }
+                _ => {
+                    vec![(Vec2::new(bounds.max().x() / 2.0, bounds.max().y() / 2.0), params.default_general_beds, params.default_icu_beds)]
+                }
+            };
+            let hospitals: Vec<(Vec2, Hospital)> = hospital_beds.into_iter().map(|(pos, general_beds, icu_beds)| {
+                let container_idx = containers.push_container(pos, ContainerType::Hospital, hospital_mixing_strategy.clone());
+                (pos, Hospital::new(container_idx, general_beds, icu_beds))
+            }).collect();
+
+            // healthcare workers work in the hospital nearest their household
+            let hospital_index = KdTree::new(hospitals.iter().map(|(pos, hospital)| (*pos, hospital.container_idx)).collect());
+            let household_container = agents.household_container.clone();
+            agents.reassign_workers(params.healthcare_worker_share, |agent_idx| {
+                let household_pos = containers.get(household_container[agent_idx as usize]).unwrap().pos;
+                hospital_index.nearest(household_pos, 1, f32::INFINITY)[0].item
+            });
+            Ok(Hospitalisation::new(params, hospitals, agents.num_agents))
+        }).transpose()?;
+
+        let regions = model.regions().map(|regions| AdminRegions {
+            codes: regions.codes().iter().map(|code| code.to_owned()).collect(),
+            household_region: regions.household_region().iter().collect(),
+        });
+
+        let importation = importation_params.map(|params| {
+            Importation::new(params, &agents, &containers, &bounds, &model.transit_graph())
+        });
+
+        let fast_graph = match load_cached_fast_graph {
+            true => { fast_graph_cache.load_or_build(&model.transit_graph()) }
+            false => { fast_graph_cache.build(&model.transit_graph()) }
         };
 
         let transit_granular_grid = nodes_to_granular_grid(&model.transit_graph(), &bounds, 100);
+        let transit_node_positions: Vec<Vec2> = model.transit_graph().nodes().iter().map(|node| *node.pos()).collect();
+        let transit_stop_index = KdTree::new(transit_node_positions.iter().enumerate().map(|(idx, &pos)| (pos, idx)).collect());
+        let commute_cache = CommuteCache::new(&agents, &containers, &transit_stop_index, &fast_graph);
+        let events = events::Events::new(&agents, &commute_cache);
+        let interventions = Interventions::new(Vec::new(), agents.num_agents);
 
-        Self {
+        Ok(Self {
             agents,
             events,
             containers,
             bounds,
             fast_graph,
             transit_granular_grid,
-        }
+            transit_node_positions,
+            transit_stop_index,
+            commute_cache,
+            congestion,
+            interventions,
+            hospitalisation,
+            importation,
+            seeding: None,
+            regions,
+            observers: Observers::default(),
+            returning_home: Vec::new(),
+        })
     }
 
-    pub fn update(&mut self, time_step: TimeStep) -> Result<(), EndOfSimulationError> {
+    /// Moves the simulation on a time step, failing once it's reached its maximum length or if an
+    /// observer fails
+    pub fn update(&mut self, time_step: TimeStep) -> Result<(), SimError> {
         if get_simulation_length_in_days().is_some() && time_step >= get_simulation_length_in_days().unwrap() * get_time_steps_per_day() {
-            return Err(EndOfSimulationError);
+            return Err(SimError::EndOfSimulation);
+        }
+        // the first time step also counts the infections seeded at the start
+        if time_step > 0 {
+            self.agents.transmission_log.reset_new_infections();
+        }
+        if time_step.is_multiple_of(get_time_steps_per_day()) {
+            self.update_day(time_step / get_time_steps_per_day(), time_step);
+        }
+
+        let mut router = Router::new(&self.commute_cache, &mut self.congestion, &self.transit_stop_index, &self.fast_graph);
+        self.events.update(time_step, &mut self.agents, &mut self.containers, &mut router, &self.interventions);
+        self.update_returning_home(time_step);
+
+        self.notify_observers(time_step)
+    }
+
+    /// Shows the observers the end of the simulation, at the time step after the last one updated
+    pub fn finish(&mut self, time_step: TimeStep) -> Result<(), SimError> {
+        let transmissions = self.agents.transmission_log.drain();
+        let mut observers = std::mem::take(&mut self.observers);
+        let result = observers.on_end(self, time_step, &transmissions);
+        self.observers = observers;
+        result
+    }
+
+    fn notify_observers(&mut self, time_step: TimeStep) -> Result<(), SimError> {
+        if self.observers.is_empty() {
+            return Ok(());
+        }
+        // the observers are taken for the duration so they can be given the rest of the sim
+        let mut observers = std::mem::take(&mut self.observers);
+        let mut result = observers.on_time_step(self, time_step);
+        if result.is_ok() && time_step.is_multiple_of(get_time_steps_per_day()) {
+            let transmissions = self.agents.transmission_log.drain();
+            result = observers.on_day(self, time_step / get_time_steps_per_day(), &transmissions);
+        }
+        self.observers = observers;
+        result
+    }
+
+    /// Updates everything that changes on a daily basis, called at the first time step of each day
+    fn update_day(&mut self, day: u32, time_step: TimeStep) {
+        if cfg!(debug_assertions) {
+            if let Err(e) = self.containers.verify_locations(&self.agents.locations) {
+                panic!("{}", e);
+            }
+        }
+
+        let has_seeds = self.seeding.as_ref().is_some_and(|seeding| seeding.has_seeds_on(day));
+        if self.interventions.isolation().is_some() || self.hospitalisation.is_some() || self.importation.is_some() || has_seeds {
+            // symptoms and severe illness appear a set time after infection, and imported and seeded
+            // infections start now, so bring every infection up to date first
+            self.containers.update_all(time_step, &mut self.agents);
+        }
+        if let Some(seeding) = self.seeding.as_mut() {
+            for agent_idx in seeding.update_day(day, &mut self.agents, &mut seeded_rng(RngStream::Seeding, &[day as u64])) {
+                self.agents.transmission_log.push(Transmission::external(time_step, agent_idx, InfectionSource::Seeded));
+            }
+        }
+        if let Some(importation) = self.importation.as_mut() {
+            for agent_idx in importation.update_day(day, &self.agents, &mut seeded_rng(RngStream::Importation, &[day as u64])) {
+                self.agents.disease_statuses[agent_idx as usize].infect();
+                self.agents.transmission_log.push(Transmission::external(time_step, agent_idx, InfectionSource::Imported));
+            }
+        }
+        if let Some(hospitalisation) = self.hospitalisation.as_mut() {
+            let updates = hospitalisation.update_day(time_step, &self.agents, &self.containers);
+            self.apply_hospital_updates(updates, time_step);
+        }
+        if self.interventions.isolation().is_some() {
+            let started_staying_home = self.interventions.update_staying_home(day, time_step, &mut self.agents, &self.containers);
+            for agent_idx in started_staying_home {
+                self.send_home(agent_idx, time_step);
+            }
+        }
+
+        let observation = if self.interventions.has_triggers() {
+            Some(Observation {
+                state_counts: StateCounts::from_agents(&self.agents),
+                num_hospitalised: self.hospitalisation.as_ref().map_or(0, |hospitalisation| hospitalisation.num_hospitalised()),
+                num_beds: self.hospitalisation.as_ref().map_or(0, |hospitalisation| hospitalisation.num_beds()),
+            })
+        } else {
+            None
+        };
+        if self.interventions.update_day(day, observation) {
+            self.containers.update_all(time_step, &mut self.agents);
+            self.interventions.apply_to_containers(&mut self.containers);
+        }
+    }
+
+    /// Moves hospitalised agents into hospital, and discharged agents back home
+    fn apply_hospital_updates(&mut self, updates: HospitalUpdates, time_step: TimeStep) {
+        for (agent_idx, hospital_idx) in updates.admissions {
+            self.relocate(agent_idx, hospital_idx, time_step);
+        }
+        for agent_idx in updates.discharges {
+            let household_idx = self.agents.household_container[agent_idx as usize];
+            self.relocate(agent_idx, household_idx, time_step);
+            self.resume_routine(agent_idx, time_step);
+        }
+        for agent_idx in updates.deaths {
+            self.agents.disease_statuses[agent_idx as usize].die();
+            self.remove(agent_idx, time_step);
+        }
+    }
+
+    /// Queues the next morning's commute for agents who've arrived home after being sent there, as
+    /// arriving would otherwise have them leave again half a day later, and at that time every day
+    fn update_returning_home(&mut self, time_step: TimeStep) {
+        if self.returning_home.is_empty() {
+            return;
+        }
+        let mut returning_home = std::mem::take(&mut self.returning_home);
+        returning_home.retain(|&handle| {
+            if self.events.is_cancelled(handle) {
+                return false;
+            }
+            let agent_idx = handle.agent_idx();
+            match self.agents.locations.get(agent_idx) {
+                Location::InContainer(container_idx) if container_idx == self.agents.household_container[agent_idx as usize] => {
+                    self.events.cancel(agent_idx);
+                    self.events.push_morning_commute(&self.agents, &self.commute_cache, agent_idx, time_step);
+                    false
+                }
+                Location::InTransit(_) => { true }
+                _ => { false }
+            }
+        });
+        self.returning_home = returning_home;
+    }
+
+    /// Cancels an agent's queued events and has them travel home straight away, if they're in a
+    /// container other than their household, their routine resuming with the next morning's commute
+    /// once they're home. Agents in transit carry on to where they're going, and those in hospital
+    /// stay there.
+    pub fn send_home(&mut self, agent_idx: u32, time_step: TimeStep) -> Option<EventHandle> {
+        if self.hospitalisation.as_ref().is_some_and(|hospitalisation| hospitalisation.is_hospitalised(agent_idx)) {
+            return None;
+        }
+        let household_idx = self.agents.household_container[agent_idx as usize];
+        match self.agents.locations.get(agent_idx) {
+            Location::InContainer(container_idx) if container_idx != household_idx => {
+                let handle = self.events.reschedule(Event {
+                    agent_idx,
+                    generation: 0,
+                    end_time_step: time_step.max(self.events.next_time_step()),
+                    event_type: EventType::Travel(TravelType {
+                        from_container_idx: NonMaxU64::new(container_idx).unwrap(),
+                        to_container_idx: NonMaxU64::new(household_idx).unwrap(),
+                        routing_type: self.commute_cache.get(agent_idx)
+                            .map_or(RoutingType::Direct(DirectRoutingType::Driving), |commute| commute.routing_type),
+                    }),
+                });
+                self.returning_home.push(handle);
+                Some(handle)
+            }
+            _ => { None }
+        }
+    }
+
+    /// Moves an agent straight into a container, cancelling their queued events and taking them out
+    /// of the container they were in, or off the road if they're in transit. Nothing is queued for
+    /// the agent afterwards.
+    pub fn relocate(&mut self, agent_idx: u32, container_idx: u64, time_step: TimeStep) {
+        self.cancel_journey(agent_idx, time_step);
+        if let Location::InContainer(current_container_idx) = self.agents.locations.get(agent_idx) {
+            self.containers.remove_inhabitant(current_container_idx, agent_idx, time_step, &mut self.agents);
+        }
+        self.containers.push_inhabitant(container_idx, agent_idx, time_step, &mut self.agents);
+        self.agents.locations.set(agent_idx, Location::InContainer(container_idx), time_step);
+    }
+
+    /// Takes an agent out of the simulation for good, e.g. having died
+    pub fn remove(&mut self, agent_idx: u32, time_step: TimeStep) {
+        self.cancel_journey(agent_idx, time_step);
+        if let Location::InContainer(container_idx) = self.agents.locations.get(agent_idx) {
+            self.containers.remove_inhabitant(container_idx, agent_idx, time_step, &mut self.agents);
         }
-        let mut fast_path_calculator = fast_paths::create_calculator(&self.fast_graph);
-        self.events.update(time_step, &mut self.agents, &mut self.containers, &self.transit_granular_grid, &self.fast_graph, &mut fast_path_calculator);
+        self.agents.locations.set(agent_idx, Location::Removed, time_step);
+    }
+
+    /// Cancels an agent's queued events, taking them off the road if they're part way through
+    /// driving somewhere
+    fn cancel_journey(&mut self, agent_idx: u32, time_step: TimeStep) {
+        self.events.cancel(agent_idx);
+        if let Location::InTransit(journey) = self.agents.locations.get(agent_idx) {
+            if journey.routing_type == RoutingType::Direct(DirectRoutingType::Driving) {
+                self.congestion.cancel_driving(journey.arrival_time_step.saturating_sub(time_step));
+            }
+        }
+    }
+
+    /// Puts an agent back into their usual routine after it's been interrupted, e.g. by `relocate`,
+    /// by queuing their next trip from wherever they are. Agents in transit are left alone.
+    pub fn resume_routine(&mut self, agent_idx: u32, time_step: TimeStep) -> Option<EventHandle> {
+        let household_idx = self.agents.household_container[agent_idx as usize];
+        match self.agents.locations.get(agent_idx) {
+            Location::InContainer(container_idx) if container_idx == household_idx => {
+                self.events.cancel(agent_idx);
+                self.events.push_morning_commute(&self.agents, &self.commute_cache, agent_idx, time_step)
+            }
+            Location::InContainer(_) => { self.send_home(agent_idx, time_step) }
+            Location::InTransit(_) | Location::Removed => { None }
+        }
+    }
 
-        Ok(())
+    /// Changes the routing type an agent commutes by, recalculating their cached commute
+    pub fn set_routing_type(&mut self, agent_idx: u32, routing_type: RoutingType) {
+        self.commute_cache.set_routing_type(agent_idx, routing_type, &self.agents, &self.containers,
+                                            &self.transit_stop_index, &self.fast_graph);
+    }
+
+    /// Replaces the transit graph's contraction hierarchies, recalculating all cached commutes by transit
+    pub fn set_fast_graph(&mut self, fast_graph: FastGraph) {
+        self.fast_graph = fast_graph;
+        self.commute_cache.refresh_transit(&self.agents, &self.containers, &self.transit_stop_index, &self.fast_graph);
+    }
+}
+
+impl<M: MixingStrategy> Sim<M> {
+    #[inline]
+    pub fn agent_location(&self, agent_idx: u32) -> Location {
+        self.agents.locations.get(agent_idx)
+    }
+
+    /// The position of an agent at a given time step, interpolated along their route if they're
+    /// travelling between containers, or at their household if they've been removed
+    pub fn agent_position(&self, agent_idx: u32, time_step: TimeStep) -> Vec2 {
+        match self.agent_location(agent_idx) {
+            Location::Removed => { self.containers.get(self.agents.household_container[agent_idx as usize]).unwrap().pos }
+            Location::InContainer(container_idx) => { self.containers.get(container_idx).unwrap().pos }
+            Location::InTransit(journey) => {
+                let from_pos = self.containers.get(journey.from_container_idx.get()).unwrap().pos;
+                let to_pos = self.containers.get(journey.to_container_idx.get()).unwrap().pos;
+                let transit_route = self.commute_cache
+                    .get_leg(&self.agents, agent_idx, journey.from_container_idx, journey.to_container_idx)
+                    .map_or(&[][..], |leg| leg.transit_route.as_slice());
+
+                let path: Vec<Vec2> = iter::once(from_pos)
+                    .chain(transit_route.iter().map(|&node_idx| self.transit_node_positions[node_idx]))
+                    .chain(iter::once(to_pos))
+                    .collect();
+                interpolate_along_path(&path, journey.progress(time_step))
+            }
+        }
+    }
+
+    /// Starts or stops recording every change in agents' locations
+    pub fn record_trajectories(&mut self, record: bool) {
+        self.agents.locations.record_trajectories(record);
+    }
+
+    /// The position, occupancy and disease states of every container, see `reporting::ContainerSnapshot`
+    pub fn container_snapshots(&self, time_step: TimeStep) -> Vec<ContainerSnapshot> {
+        container_snapshots(time_step, &self.agents, &self.containers)
+    }
+
+    /// Starts or stops recording every new infection, see `Agents::transmission_log`
+    pub fn record_transmissions(&mut self, record: bool) {
+        self.agents.transmission_log.record(record);
+    }
+
+    /// Registers an observer to be shown the sim at the given interval, and when it's finished.
+    /// Once any are registered the transmission log is drained each day, its entries being given to
+    /// the observers.
+    ///
+    /// Observers aren't cloned along with the sim, so a clone has none registered.
+    pub fn add_observer(&mut self, observer: Box<dyn SimObserver<M> + Send + Sync>, interval: ObserverInterval) {
+        self.observers.push(observer, interval);
     }
 }
 
@@ -114,6 +470,14 @@ pub struct SimBuilder<'a, P: Into<PathBuf>> {
     synthetic_environment_dir: P,
     model_name: &'a str,
     load_fast_graph_from_disk: bool,
+    fast_graph_cache: FastGraphCache,
+    record_trajectories: bool,
+    record_transmissions: bool,
+    scenario_file: Option<PathBuf>,
+    seeding_file: Option<PathBuf>,
+    hospitalisation: Option<HospitalisationParams>,
+    importation: Option<ImportationParams>,
+    importation_file: Option<PathBuf>,
     walking_speed_kph: f32,
     cycling_speed_kph: f32,
     driving_speed_kph: f32,
@@ -126,6 +490,14 @@ impl<'a, P> SimBuilder<'a, P> where P: Into<PathBuf> {
             synthetic_environment_dir,
             model_name,
             load_fast_graph_from_disk: false,
+            fast_graph_cache: FastGraphCache::default(),
+            record_trajectories: false,
+            record_transmissions: false,
+            scenario_file: None,
+            seeding_file: None,
+            hospitalisation: None,
+            importation: None,
+            importation_file: None,
             walking_speed_kph: 5.0,
             cycling_speed_kph: 23.5,
             driving_speed_kph: 60.0,
@@ -147,6 +519,12 @@ impl<'a, P> SimBuilder<'a, P> where P: Into<PathBuf> {
         self
     }
 
+    /// Seeds every random draw, see `shared::seeded_rng`, or `None` for a random seed
+    pub fn seed(mut self, seed: Option<u64>) -> Self {
+        self.global_params.seed = seed.unwrap_or_else(rand::random);
+        self
+    }
+
     pub fn walking_speed_kph(mut self, walking_speed_kph: f32) -> Self {
         self.walking_speed_kph = walking_speed_kph;
         self
@@ -162,15 +540,119 @@ impl<'a, P> SimBuilder<'a, P> where P: Into<PathBuf> {
         self
     }
 
+    /// The number of agents per time-step the roads can carry before driving times rise sharply,
+    /// `None` disables congestion
+    pub fn road_capacity(mut self, road_capacity: Option<f32>) -> Self {
+        self.global_params.road_capacity = road_capacity;
+        self
+    }
+
+    /// The alpha and beta parameters of the BPR function used to scale driving times by congestion
+    pub fn congestion_bpr_params(mut self, alpha: f32, beta: f32) -> Self {
+        self.global_params.congestion_alpha = alpha;
+        self.global_params.congestion_beta = beta;
+        self
+    }
+
+    /// Whether to load the transit graph's contraction hierarchies from the cache, they're rebuilt
+    /// if the cache doesn't contain a graph matching the model
     pub fn load_fast_graph_from_disk(mut self, load_from_disk: bool) -> Self {
         self.load_fast_graph_from_disk = load_from_disk;
         self
     }
 
-    pub fn build(mut self) -> Sim<Uniform> {
+    pub fn fast_graph_cache_dir<D: Into<PathBuf>>(mut self, dir: D) -> Self {
+        self.fast_graph_cache.dir = dir.into();
+        self
+    }
+
+    pub fn fast_graph_hierarchy_depth_factor(mut self, hierarchy_depth_factor: f32) -> Self {
+        self.fast_graph_cache.hierarchy_depth_factor = hierarchy_depth_factor;
+        self
+    }
+
+    /// Whether to log every change in agents' locations, see `AgentLocations::trajectory_log`
+    pub fn record_trajectories(mut self, record_trajectories: bool) -> Self {
+        self.record_trajectories = record_trajectories;
+        self
+    }
+
+    /// Whether to log every new infection and who it's attributed to, see `Agents::transmission_log`
+    pub fn record_transmissions(mut self, record_transmissions: bool) -> Self {
+        self.record_transmissions = record_transmissions;
+        self
+    }
+
+    /// A JSON scenario file describing the interventions to apply during the simulation
+    pub fn scenario_file<S: Into<PathBuf>>(mut self, scenario_file: Option<S>) -> Self {
+        self.scenario_file = scenario_file.map(|scenario_file| scenario_file.into());
+        self
+    }
+
+    /// A JSON file describing the infections to seed, replacing the `seed_infection_chance` at the
+    /// start, see `disease::SeedingParams`
+    pub fn seeding_file<S: Into<PathBuf>>(mut self, seeding_file: Option<S>) -> Self {
+        self.seeding_file = seeding_file.map(|seeding_file| seeding_file.into());
+        self
+    }
+
+    /// Hospitalises severe infections, some of whom die, or `None` for infections to never be severe
+    pub fn hospitalisation(mut self, hospitalisation: Option<HospitalisationParams>) -> Self {
+        self.hospitalisation = hospitalisation;
+        self
+    }
+
+    /// Infections imported from outside the modelled region over the course of the simulation
+    pub fn importation(mut self, importation: Option<ImportationParams>) -> Self {
+        self.importation = importation;
+        self
+    }
+
+    /// A JSON file of the importation rates over time, read in place of any `importation`, see
+    /// `disease::ImportationParams`
+    pub fn importation_file<S: Into<PathBuf>>(mut self, importation_file: Option<S>) -> Self {
+        self.importation_file = importation_file.map(|importation_file| importation_file.into());
+        self
+    }
+
+    /// Loads the model and the files describing the run, failing if any of them can't be read or
+    /// don't match the model
+    pub fn build(mut self) -> Result<Sim<Uniform>, Box<dyn Error>> {
         self.global_params.walking_speed = self.walking_speed_kph * 1000.0 * 24.0 / self.global_params.time_steps_per_day as f32;
         self.global_params.cycling_speed = self.cycling_speed_kph * 1000.0 * 24.0 / self.global_params.time_steps_per_day as f32;
         self.global_params.driving_speed = self.driving_speed_kph * 1000.0 * 24.0 / self.global_params.time_steps_per_day as f32;
-        Sim::new(self.synthetic_environment_dir, self.model_name, self.load_fast_graph_from_disk, self.global_params)
+        let importation = match self.importation_file {
+            Some(importation_file) => {
+                Some(ImportationParams::from_file(&importation_file)
+                    .map_err(|e| format!("importation file {} couldn't be read: {}", importation_file.display(), e))?)
+            }
+            None => { self.importation }
+        };
+        let model_name = self.model_name;
+        let mut sim = Sim::new(self.synthetic_environment_dir, model_name, self.load_fast_graph_from_disk, self.fast_graph_cache,
+                               self.global_params, self.hospitalisation, importation)
+            .map_err(|e| format!("model {} couldn't be loaded: {}", model_name, e))?;
+        sim.record_trajectories(self.record_trajectories);
+        if let Some(seeding_file) = self.seeding_file {
+            let seeding = SeedingParams::from_file(&seeding_file)
+                .and_then(|params| Seeding::new(params, &sim.agents, &sim.containers));
+            sim.seeding = Some(seeding.map_err(|e| format!("seeding file {} couldn't be read: {}", seeding_file.display(), e))?);
+            // the seeds replace the infections drawn with the seed infection chance
+            sim.agents.disease_statuses.iter_mut().for_each(|status| *status = DiseaseStatus::susceptible());
+        }
+        sim.record_transmissions(self.record_transmissions);
+        // those infected with the seed infection chance were infected before the log existed
+        for agent_idx in 0..sim.agents.num_agents {
+            if sim.agents.disease_statuses[agent_idx as usize].is_infected() {
+                sim.agents.transmission_log.push(Transmission::external(0, agent_idx, InfectionSource::Seeded));
+            }
+        }
+        if let Some(scenario_file) = self.scenario_file {
+            sim.interventions = Interventions::from_scenario_file(&scenario_file, &sim.agents)
+                .map_err(|e| format!("scenario file {} couldn't be read: {}", scenario_file.display(), e))?;
+            let lookback_time_steps = sim.interventions.contact_lookback_time_steps();
+            sim.agents.locations.record_contacts(lookback_time_steps, 0);
+        }
+        Ok(sim)
     }
 }
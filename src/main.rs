@@ -1,5 +1,3 @@
-use std::time::Instant;
-
 use pixels::{Error, Pixels, SurfaceTexture};
 use structopt::StructOpt;
 use winit::dpi::LogicalSize;
@@ -8,7 +6,14 @@ use winit::event_loop::{ControlFlow, EventLoop};
 use winit::window::WindowBuilder;
 use winit_input_helper::WinitInputHelper;
 
-use outbreak_sim::reporting::{intialise_reporting_files, write_concluding_metrics, write_intermediary_metric};
+use outbreak_sim::disease::{HospitalisationParams, ImportationParams};
+use outbreak_sim::reporting::{create_age_report_file, create_hospital_report_file, create_spatial_report_file, create_testing_report_file,
+                              create_transmission_report_file, intialise_reporting_files, AgeBands, AgeReport, AgeReporter,
+                              ConcludingReporter, ContainerSnapshotReporter, HospitalReporter, IntermediaryReporter, ObserverInterval,
+                              ReportDir, ReportFormat, ReproductionNumbers, ReproductionReporter, SnapshotFormat, SnapshotTimes, SpatialReport,
+                              SpatialReporter, SpatialUnit, TestingReporter, TransmissionReporter};
+use outbreak_sim::SimError;
+use outbreak_sim::shared::get_time_steps_per_day;
 use outbreak_sim::shared::types::TimeStep;
 
 const SCREEN_WIDTH: u32 = 950;
@@ -40,6 +45,74 @@ struct Cli {
     /// The chance an agent is exposed/infected at the start of the simulation
     #[structopt(default_value="0.001", long)]
     seed_infection_chance: f32,
+    /// Seeds every random draw, so runs with the same seed and parameters are the same, leave
+    /// blank for a random seed
+    #[structopt(long)]
+    seed: Option<u64>,
+    /// A JSON file describing the infections to seed, replacing the seed infection chance
+    #[structopt(long, parse(from_os_str))]
+    seeding_file: Option<std::path::PathBuf>,
+    /// The number of agents per time-step the roads can carry before driving times rise sharply,
+    /// leave blank to disable congestion
+    #[structopt(long)]
+    road_capacity: Option<f32>,
+    /// A JSON scenario file describing the interventions to apply during the simulation
+    #[structopt(long, parse(from_os_str))]
+    scenario: Option<std::path::PathBuf>,
+    /// Hospitalise severe infections with the default hospitalisation parameters, some of whom die
+    #[structopt(long)]
+    hospitalisation: bool,
+    /// The expected number of infections imported from outside the modelled region each day
+    #[structopt(long, conflicts_with = "importation-file")]
+    importation_rate: Option<f32>,
+    /// A JSON file of the importation rates over time and which agents they target
+    #[structopt(long, parse(from_os_str))]
+    importation_file: Option<std::path::PathBuf>,
+    /// Log every new infection and who it's attributed to in `transmissions.csv`
+    #[structopt(long)]
+    record_transmissions: bool,
+    /// Estimate the reproduction numbers over time from the new infections, written to `rt.csv`
+    /// when the simulation ends
+    #[structopt(long)]
+    estimate_rt: bool,
+    /// Report the disease states of each cell of a grid with this many rows in `spatial.csv`
+    #[structopt(long, conflicts_with = "spatial-regions")]
+    spatial_grid_rows: Option<u32>,
+    /// Report the disease states of each of the model's administrative regions in `spatial.csv`
+    #[structopt(long)]
+    spatial_regions: bool,
+    /// Report incidence, prevalence and outcomes per age band in `ages.csv`, given by a comma
+    /// separated list of the youngest age in each band, e.g. "0,18,65"
+    #[structopt(long)]
+    age_bands: Option<AgeBands>,
+    /// Snapshot the position, occupancy and disease states of every container at these time steps,
+    /// given as a comma separated list
+    #[structopt(long, use_delimiter = true)]
+    snapshot_time_steps: Vec<TimeStep>,
+    /// Snapshot every container at the start of every this many days
+    #[structopt(long)]
+    snapshot_every_days: Option<u32>,
+    /// The format of the container snapshots: csv, or geojson for a file per snapshot
+    #[structopt(default_value = "csv", long)]
+    snapshot_format: SnapshotFormat,
+    /// The format of the intermediary, transmission, spatial and age reports: csv, or parquet or
+    /// arrow when built with the `columnar` feature
+    #[structopt(default_value = "csv", long)]
+    report_format: ReportFormat,
+    /// The directory the reports are written to, within a directory for the model and one for the
+    /// run
+    #[structopt(default_value = "reports", long, parse(from_os_str))]
+    report_dir: std::path::PathBuf,
+    /// The name of the run's report directory, the iteration by default, which has a suffix
+    /// appended if a run with it already exists
+    #[structopt(long)]
+    run_id: Option<String>,
+    /// Write the reports to the existing run directory with the same run ID, replacing its reports
+    #[structopt(long)]
+    replace_reports: bool,
+    /// The directory used to cache the preprocessed transit graph
+    #[structopt(default_value="fast_paths", long, parse(from_os_str))]
+    fast_graph_cache_dir: std::path::PathBuf,
 }
 
 
@@ -50,16 +123,91 @@ fn main() -> Result<(), Error> {
 
     let mut time_step: TimeStep = 0;
 
-    let mut sim = outbreak_sim::SimBuilder::new(&synthetic_environment_dir, &model_name)
-        .load_fast_graph_from_disk(false)
+    let sim = outbreak_sim::SimBuilder::new(&synthetic_environment_dir, &model_name)
+        .load_fast_graph_from_disk(true)
+        .fast_graph_cache_dir(&args.fast_graph_cache_dir)
         .sim_length_days(args.sim_length_days)
         .time_steps_per_day(args.time_steps_per_day)
         .seed_infection_chance(args.seed_infection_chance)
+        .seed(args.seed)
+        .seeding_file(args.seeding_file.as_ref())
+        .road_capacity(args.road_capacity)
+        .scenario_file(args.scenario.as_ref())
+        .hospitalisation(if args.hospitalisation { Some(HospitalisationParams::default()) } else { None })
+        .importation(args.importation_rate.map(ImportationParams::constant))
+        .importation_file(args.importation_file.as_ref())
+        .record_transmissions(args.record_transmissions || args.estimate_rt)
         .build();
+    let mut sim = match sim {
+        Ok(sim) => { sim }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     println!("{:?}", args);
 
-    let (mut intermediary_report_writer, concluding_report_file) = intialise_reporting_files("reports/".to_owned() + &model_name, args.iteration, true).unwrap();
+    let run_id = args.run_id.clone().unwrap_or_else(|| args.iteration.to_string());
+    let report_dir = match ReportDir::create(&args.report_dir, &model_name, &run_id, args.replace_reports) {
+        Ok(report_dir) => { report_dir }
+        Err(e) => {
+            eprintln!("report directory for run {} couldn't be created in {}", run_id, args.report_dir.display());
+            panic!("{}", e);
+        }
+    };
+    let intermediary_report_writer = intialise_reporting_files(&report_dir, args.report_format).unwrap();
+    sim.add_observer(Box::new(IntermediaryReporter { writer: intermediary_report_writer }), ObserverInterval::default());
+    let concluding_reporter = ConcludingReporter::new(report_dir.clone(), synthetic_environment_dir.join(model_name.to_owned() + ".txt"),
+                                                      args.iteration, args.age_bands.clone().unwrap_or_default());
+    sim.add_observer(Box::new(concluding_reporter), ObserverInterval::default());
+    if sim.interventions.testing().is_some() {
+        let writer = create_testing_report_file(&report_dir).unwrap();
+        sim.add_observer(Box::new(TestingReporter { writer }), ObserverInterval::default());
+    }
+    if sim.hospitalisation.is_some() {
+        let writer = create_hospital_report_file(&report_dir).unwrap();
+        sim.add_observer(Box::new(HospitalReporter { writer }), ObserverInterval::default());
+    }
+    if args.record_transmissions {
+        let writer = create_transmission_report_file(&report_dir, args.report_format).unwrap();
+        sim.add_observer(Box::new(TransmissionReporter { writer }), ObserverInterval::default());
+    }
+    let spatial_unit = match (args.spatial_grid_rows, args.spatial_regions) {
+        (Some(rows), _) => { Some(SpatialUnit::Grid { rows }) }
+        (None, true) => { Some(SpatialUnit::Regions) }
+        (None, false) => { None }
+    };
+    if let Some(unit) = spatial_unit {
+        let report = match SpatialReport::new(&unit, &sim.agents, &sim.containers, &sim.bounds, sim.regions.as_ref()) {
+            Ok(report) => { report }
+            Err(e) => {
+                eprintln!("the spatial report couldn't be set up");
+                panic!("{}", e);
+            }
+        };
+        let writer = create_spatial_report_file(&report_dir, args.report_format).unwrap();
+        sim.add_observer(Box::new(SpatialReporter { report, writer }), ObserverInterval::default());
+    }
+    if let Some(bands) = args.age_bands.clone() {
+        let report = AgeReport::new(bands, &sim.agents);
+        let writer = create_age_report_file(&report_dir, args.report_format).unwrap();
+        sim.add_observer(Box::new(AgeReporter { report, writer }), ObserverInterval::default());
+    }
+    let snapshot_times = SnapshotTimes {
+        time_steps: args.snapshot_time_steps.clone(),
+        every: args.snapshot_every_days.map(|days| days * get_time_steps_per_day()),
+    };
+    if !snapshot_times.is_empty() {
+        let reporter = ContainerSnapshotReporter::new(report_dir.clone(), args.snapshot_format, snapshot_times).unwrap();
+        sim.add_observer(Box::new(reporter), ObserverInterval::default());
+    }
+    if args.estimate_rt {
+        sim.add_observer(Box::new(ReproductionReporter {
+            reproduction_numbers: ReproductionNumbers::new(sim.agents.num_agents),
+            report_dir: report_dir.clone(),
+        }), ObserverInterval::default());
+    }
 
     println!("{} Agents with a workplace", sim.agents.occupational_container.iter().filter(|idx| idx.is_some()).count());
 
@@ -78,18 +226,16 @@ fn main() -> Result<(), Error> {
     let mut pixels = {
         let window_size = window.inner_size();
         let surface_texture = SurfaceTexture::new(window_size.width, window_size.height, &window);
-        Pixels::new(WORLD_WIDTH as u32, WORLD_WIDTH as u32, surface_texture)?
+        Pixels::new(WORLD_WIDTH, WORLD_WIDTH, surface_texture)?
     };
     let mut world = graphics::WorldGrid::new_empty(WORLD_HEIGHT as usize, WORLD_WIDTH as usize);
 
-    let start_time = Instant::now();
     event_loop.run(move |event, _, control_flow| {
         // User-ended simulation
         if let Event::LoopDestroyed = event {
-            write_concluding_metrics(&concluding_report_file, time_step,
-                                     Instant::now().duration_since(start_time),
-                                     synthetic_environment_dir.join(model_name.to_owned() + ".txt"),
-            ).unwrap()
+            if let Err(e) = sim.finish(time_step) {
+                eprintln!("{}", e);
+            }
         }
 
         // Draw the current frame
@@ -120,15 +266,22 @@ fn main() -> Result<(), Error> {
 
             // Update internal state and request a redraw
             for _ in 0..args.iterations_per_render {
-                if sim.update(time_step).is_err() {
-                    *control_flow = ControlFlow::Exit;
-                    return;
+                match sim.update(time_step) {
+                    Ok(()) => {}
+                    Err(SimError::EndOfSimulation) => {
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
+                    Err(e) => {
+                        eprintln!("{} at time step {}", e, time_step);
+                        *control_flow = ControlFlow::Exit;
+                        return;
+                    }
                 }
-                write_intermediary_metric(&mut intermediary_report_writer, time_step, &sim.agents).unwrap();
                 time_step += 1;
             }
 
-            world.update(&sim);
+            world.update(&sim, time_step);
             window.request_redraw();
         }
     });
@@ -0,0 +1,154 @@
+use std::error::Error;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agents::Agents;
+use crate::disease::Hospitalisation;
+use crate::reporting::{ReportWriter, StateCounts};
+use crate::reporting::stratified::StratifiedCounts;
+
+/// Age bands given by the youngest age in each, in increasing order, the last band having no upper
+/// bound
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AgeBands {
+    lower_bounds: Vec<u8>,
+}
+
+impl AgeBands {
+    pub fn new(mut lower_bounds: Vec<u8>) -> Self {
+        lower_bounds.sort_unstable();
+        lower_bounds.dedup();
+        if lower_bounds.first() != Some(&0) {
+            lower_bounds.insert(0, 0);
+        }
+        AgeBands { lower_bounds }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.lower_bounds.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.lower_bounds.is_empty() }
+
+    /// The index of the band the age is in
+    #[inline]
+    pub fn band_of(&self, age: u8) -> usize {
+        self.lower_bounds.iter().rposition(|&lower_bound| lower_bound <= age).unwrap_or(0)
+    }
+
+    /// The name of a band, e.g. "18-64" or "65+"
+    pub fn name(&self, band: usize) -> String {
+        match self.lower_bounds.get(band + 1) {
+            Some(next_lower_bound) => { format!("{}-{}", self.lower_bounds[band], next_lower_bound - 1) }
+            None => { format!("{}+", self.lower_bounds[band]) }
+        }
+    }
+}
+
+impl Default for AgeBands {
+    /// Ten year bands up to 80+
+    fn default() -> Self {
+        AgeBands::new((0..=80).step_by(10).collect())
+    }
+}
+
+impl FromStr for AgeBands {
+    type Err = std::num::ParseIntError;
+
+    /// Parses a comma separated list of the lower bounds, e.g. "0,18,65"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower_bounds = s.split(',').map(|bound| bound.trim().parse()).collect::<Result<Vec<u8>, _>>()?;
+        Ok(AgeBands::new(lower_bounds))
+    }
+}
+
+/// A measure of an age band on a day, as written to the age report
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AgeMetric {
+    day: u32,
+    age_band: String,
+    measure: &'static str,
+    value: usize,
+}
+
+/// # Age Report
+/// Incidence, prevalence and outcomes per age band per day, written in long format with a row for
+/// each measure.
+#[derive(Clone)]
+pub struct AgeReport {
+    bands: AgeBands,
+    stratified: StratifiedCounts,
+}
+
+impl AgeReport {
+    pub fn new(bands: AgeBands, agents: &Agents) -> Self {
+        let band_of_agent = agents.ages.iter().map(|&age| bands.band_of(age) as u32).collect();
+        let stratified = StratifiedCounts::new(bands.len(), band_of_agent, agents);
+        AgeReport { bands, stratified }
+    }
+
+    #[inline]
+    pub fn bands(&self) -> &AgeBands {
+        &self.bands
+    }
+
+    /// The counts of each disease state in each age band
+    pub fn counts(&self, agents: &Agents) -> Vec<StateCounts> {
+        self.stratified.counts(agents)
+    }
+
+    /// Writes the measures of every age band, the incidence and deaths being those since the
+    /// previous day written
+    pub fn write(&mut self, report_writer: &mut ReportWriter<AgeMetric>, day: u32, agents: &Agents,
+                 hospitalisation: Option<&Hospitalisation>) -> Result<(), Box<dyn Error>> {
+        let mut hospitalised = vec![0; self.bands.len()];
+        if let Some(hospitalisation) = hospitalisation {
+            for (agent_idx, &band) in self.stratified.group_of_agent().iter().enumerate() {
+                if hospitalisation.is_hospitalised(agent_idx as u32) {
+                    hospitalised[band as usize] += 1;
+                }
+            }
+        }
+
+        for (band, (counts, previous)) in self.stratified.update(agents).iter().enumerate() {
+            let age_band = self.bands.name(band);
+            let measures = [
+                ("population", counts.total()),
+                ("incidence", counts.incidence_since(previous)),
+                ("prevalence", counts.num_exposed + counts.num_infectious),
+                ("susceptible", counts.num_susceptible),
+                ("exposed", counts.num_exposed),
+                ("infectious", counts.num_infectious),
+                ("recovered", counts.num_recovered),
+                ("hospitalised", hospitalised[band]),
+                ("new_deaths", counts.num_dead.saturating_sub(previous.num_dead)),
+                ("dead", counts.num_dead),
+            ];
+            for &(measure, value) in measures.iter() {
+                report_writer.write(AgeMetric { day, age_band: age_band.clone(), measure, value })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_bands() {
+        let bands: AgeBands = "65, 18".parse().unwrap();
+        assert_eq!(bands.len(), 3);
+        assert_eq!(bands.band_of(0), 0);
+        assert_eq!(bands.band_of(17), 0);
+        assert_eq!(bands.band_of(18), 1);
+        assert_eq!(bands.band_of(90), 2);
+        assert_eq!(bands.name(0), "0-17");
+        assert_eq!(bands.name(1), "18-64");
+        assert_eq!(bands.name(2), "65+");
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::ipc::writer::FileWriter;
+use arrow::json::ReaderBuilder;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use parquet::format::KeyValue;
+use serde::Serialize;
+
+use crate::disease::Transmission;
+use crate::interventions::TestingMetric;
+use crate::reporting::{AgeMetric, ContainerSnapshot, HospitalMetric, IntermediaryMetric, SpatialMetric};
+use crate::shared::GLOBAL_PARAMS;
+
+/// The number of rows buffered before they're written as a record batch
+pub const DEFAULT_BATCH_SIZE: usize = 8192;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ColumnarFormat {
+    Parquet,
+    ArrowIpc,
+}
+
+impl ColumnarFormat {
+    #[inline]
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ColumnarFormat::Parquet => { "parquet" }
+            ColumnarFormat::ArrowIpc => { "arrow" }
+        }
+    }
+}
+
+enum Sink {
+    Parquet(ArrowWriter<File>),
+    ArrowIpc(FileWriter<File>),
+}
+
+/// # Columnar Writer
+/// Writes rows of a report to a Parquet or Arrow IPC file in batches.
+///
+/// Each row type has a fixed schema, see `ColumnarRow`, so a column has the same type in every
+/// report whatever the values written, e.g. one that's always `None`. The file's metadata holds the simulation's `GlobalSimParams`, under
+/// `outbreak_sim.global_params`, along with the version of the simulation.
+///
+/// The file isn't valid until `finish` is called.
+pub struct ColumnarWriter<T> {
+    path: PathBuf,
+    format: ColumnarFormat,
+    batch_size: usize,
+    rows: Vec<T>,
+    schema: Option<SchemaRef>,
+    sink: Option<Sink>,
+}
+
+impl<T: ColumnarRow> ColumnarWriter<T> {
+    pub fn new(path: PathBuf, format: ColumnarFormat, batch_size: usize) -> Self {
+        ColumnarWriter { path, format, batch_size: batch_size.max(1), rows: Vec::new(), schema: None, sink: None }
+    }
+
+    pub fn write(&mut self, row: T) -> Result<(), Box<dyn Error>> {
+        self.rows.push(row);
+        if self.rows.len() >= self.batch_size {
+            self.write_batch()?;
+        }
+        Ok(())
+    }
+
+    /// Writes any buffered rows and the file's footer, after which nothing else can be written
+    pub fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        self.write_batch()?;
+        match self.sink.take() {
+            Some(Sink::Parquet(writer)) => { writer.close()?; }
+            Some(Sink::ArrowIpc(mut writer)) => { writer.finish()?; }
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn write_batch(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.rows.is_empty() {
+            return Ok(());
+        }
+        let schema = match &self.schema {
+            Some(schema) => { schema.clone() }
+            None => {
+                let schema = Self::schema()?;
+                self.sink = Some(self.create_sink(&schema)?);
+                self.schema = Some(schema.clone());
+                schema
+            }
+        };
+
+        let mut decoder = ReaderBuilder::new(schema).with_coerce_primitive(true).build_decoder()?;
+        decoder.serialize(&self.rows)?;
+        let batch: Option<RecordBatch> = decoder.flush()?;
+        if let Some(batch) = batch {
+            match self.sink.as_mut() {
+                Some(Sink::Parquet(writer)) => { writer.write(&batch)?; }
+                Some(Sink::ArrowIpc(writer)) => { writer.write(&batch)?; }
+                None => { return Err(format!("{} has already been finished", self.path.display()).into()); }
+            }
+        }
+        self.rows.clear();
+        Ok(())
+    }
+
+    fn schema() -> Result<SchemaRef, Box<dyn Error>> {
+        Ok(Arc::new(Schema::new(T::fields()).with_metadata(run_metadata()?)))
+    }
+
+    fn create_sink(&self, schema: &SchemaRef) -> Result<Sink, Box<dyn Error>> {
+        let file = File::create(&self.path)?;
+        Ok(match self.format {
+            ColumnarFormat::Parquet => {
+                let key_value_metadata = schema.metadata().iter()
+                    .map(|(key, value)| KeyValue::new(key.to_owned(), value.to_owned()))
+                    .collect();
+                let properties = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .set_key_value_metadata(Some(key_value_metadata))
+                    .build();
+                Sink::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(properties))?)
+            }
+            ColumnarFormat::ArrowIpc => { Sink::ArrowIpc(FileWriter::try_new(file, schema)?) }
+        })
+    }
+}
+
+/// A row of a report that can be written in a columnar format, its fields being in the order
+/// they're serialized, with a type matching how they're serialized
+pub trait ColumnarRow: Serialize {
+    fn fields() -> Vec<Field>;
+}
+
+#[inline]
+fn field(name: &str, data_type: DataType) -> Field {
+    Field::new(name, data_type, false)
+}
+
+#[inline]
+fn nullable_field(name: &str, data_type: DataType) -> Field {
+    Field::new(name, data_type, true)
+}
+
+impl ColumnarRow for IntermediaryMetric {
+    fn fields() -> Vec<Field> {
+        let mut fields = vec![field("time_step", DataType::UInt32)];
+        fields.extend(["num_susceptible", "num_exposed", "num_infectious", "num_recovered", "num_dead", "num_hospitalised",
+            "num_staying_home", "new_infections_household", "new_infections_workplace", "new_infections_school",
+            "new_infections_hospital", "new_infections_seeded", "new_infections_imported"].iter()
+            .map(|name| field(name, DataType::UInt64)));
+        fields.push(field("active_interventions", DataType::Utf8));
+        fields
+    }
+}
+
+impl ColumnarRow for TestingMetric {
+    fn fields() -> Vec<Field> {
+        vec![
+            field("day", DataType::UInt32),
+            field("tests_performed", DataType::UInt32),
+            field("positives", DataType::UInt32),
+            field("contacts_traced", DataType::UInt32),
+            field("queue_length", DataType::UInt64),
+        ]
+    }
+}
+
+impl ColumnarRow for HospitalMetric {
+    fn fields() -> Vec<Field> {
+        vec![
+            field("time_step", DataType::UInt32),
+            field("container_idx", DataType::UInt64),
+            field("general_beds", DataType::UInt32),
+            field("general_occupied", DataType::UInt32),
+            field("icu_beds", DataType::UInt32),
+            field("icu_occupied", DataType::UInt32),
+            field("over_capacity", DataType::UInt32),
+        ]
+    }
+}
+
+impl ColumnarRow for Transmission {
+    fn fields() -> Vec<Field> {
+        vec![
+            field("time_step", DataType::UInt32),
+            field("infectee", DataType::UInt32),
+            nullable_field("infector", DataType::UInt32),
+            field("source", DataType::Utf8),
+            nullable_field("container_idx", DataType::UInt64),
+            nullable_field("container_type", DataType::Utf8),
+            field("strain", DataType::UInt16),
+        ]
+    }
+}
+
+impl ColumnarRow for SpatialMetric {
+    fn fields() -> Vec<Field> {
+        let mut fields = vec![field("day", DataType::UInt32), field("unit", DataType::Utf8)];
+        fields.extend(["num_susceptible", "num_exposed", "num_infectious", "num_recovered", "num_dead", "incidence"].iter()
+            .map(|name| field(name, DataType::UInt64)));
+        fields
+    }
+}
+
+impl ColumnarRow for AgeMetric {
+    fn fields() -> Vec<Field> {
+        vec![
+            field("day", DataType::UInt32),
+            field("age_band", DataType::Utf8),
+            field("measure", DataType::Utf8),
+            field("value", DataType::UInt64),
+        ]
+    }
+}
+
+impl ColumnarRow for ContainerSnapshot {
+    fn fields() -> Vec<Field> {
+        let mut fields = vec![
+            field("time_step", DataType::UInt32),
+            field("container_idx", DataType::UInt64),
+            field("container_type", DataType::Utf8),
+            field("x", DataType::Float32),
+            field("y", DataType::Float32),
+        ];
+        fields.extend(["occupancy", "num_susceptible", "num_exposed", "num_infectious", "num_recovered", "num_dead"].iter()
+            .map(|name| field(name, DataType::UInt64)));
+        fields
+    }
+}
+
+/// The metadata embedded in every columnar report
+fn run_metadata() -> Result<HashMap<String, String>, Box<dyn Error>> {
+    let mut metadata = HashMap::new();
+    if let Some(global_params) = GLOBAL_PARAMS.get() {
+        metadata.insert("outbreak_sim.global_params".to_owned(), serde_json::to_string(global_params)?);
+    }
+    metadata.insert("outbreak_sim.version".to_owned(), env!("CARGO_PKG_VERSION").to_owned());
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::ipc::reader::FileReader;
+
+    use crate::disease::InfectionSource;
+
+    use super::*;
+
+    #[test]
+    fn test_columns_always_none_keep_their_type() {
+        let path = std::env::temp_dir().join(format!("outbreak_sim_columnar_{}.arrow", std::process::id()));
+        let mut writer = ColumnarWriter::new(path.clone(), ColumnarFormat::ArrowIpc, 2);
+        for infectee in 0..3 {
+            writer.write(Transmission::external(0, infectee, InfectionSource::Seeded)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        let schema = reader.schema();
+        assert_eq!(schema.field_with_name("infector").unwrap().data_type(), &DataType::UInt32);
+        assert_eq!(schema.field_with_name("container_idx").unwrap().data_type(), &DataType::UInt64);
+        assert_eq!(schema.field_with_name("container_type").unwrap().data_type(), &DataType::Utf8);
+        let num_rows: usize = reader.map(|batch| batch.unwrap().num_rows()).sum();
+        assert_eq!(num_rows, 3);
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::agents::{Agents, TrajectoryEntry};
+use crate::disease::{Hospital, Hospitalisation, HospitalisationParams, State, Transmission};
+use crate::interventions::{Interventions, TestingMetric};
+use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+use crate::shared::types::TimeStep;
+
+pub use age::{AgeBands, AgeMetric, AgeReport};
+#[cfg(feature = "columnar")]
+pub use columnar::{ColumnarFormat, ColumnarRow, ColumnarWriter, DEFAULT_BATCH_SIZE};
+pub use observer::{AgeReporter, ConcludingReporter, ContainerSnapshotReporter, HospitalReporter, IntermediaryReporter, ObserverInterval,
+                   Observers, ReproductionReporter, SimObserver, SpatialReporter, TestingReporter, TransmissionReporter};
+pub use output::{ReportDir, ReportFormat, ReportRow, ReportWriter, write_json_atomically};
+pub use reproduction::{ReproductionMetric, ReproductionNumbers};
+pub use snapshot::{container_snapshots, ContainerSnapshot, SnapshotFormat, snapshots_to_geojson, SnapshotTimes};
+pub use spatial::{AdminRegions, SpatialMetric, SpatialReport, SpatialUnit};
+pub use summary::{AgeAttackRate, EpidemicSummary, EpidemicSummaryMetric};
+
+mod age;
+#[cfg(feature = "columnar")]
+mod columnar;
+mod observer;
+mod output;
+mod reproduction;
+mod snapshot;
+mod spatial;
+mod stratified;
+mod summary;
+
+/// An insight into a simulation's state _during_ simulation
+#[derive(Serialize, Deserialize)]
+pub struct IntermediaryMetric {
+    time_step: TimeStep,
+    num_susceptible: usize,
+    num_exposed: usize,
+    num_infectious: usize,
+    num_recovered: usize,
+    num_dead: usize,
+    num_hospitalised: usize,
+    num_staying_home: usize,
+    new_infections_household: usize,
+    new_infections_workplace: usize,
+    new_infections_school: usize,
+    new_infections_hospital: usize,
+    new_infections_seeded: usize,
+    new_infections_imported: usize,
+    active_interventions: String,
+}
+
+/// The number of agents in each disease state
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct StateCounts {
+    pub num_susceptible: usize,
+    pub num_exposed: usize,
+    pub num_infectious: usize,
+    pub num_recovered: usize,
+    pub num_dead: usize,
+}
+
+impl StateCounts {
+    pub fn from_agents(agents: &Agents) -> Self {
+        let mut counts = StateCounts::default();
+        for status in &agents.disease_statuses {
+            counts.add(status.state);
+        }
+        counts
+    }
+
+    #[inline]
+    pub fn add(&mut self, state: State) {
+        match state {
+            State::Susceptible => { self.num_susceptible += 1 }
+            State::Exposed => { self.num_exposed += 1 }
+            State::Infectious => { self.num_infectious += 1 }
+            State::Recovered => { self.num_recovered += 1 }
+            State::Dead => { self.num_dead += 1 }
+        }
+    }
+
+    #[inline]
+    pub fn total(&self) -> usize {
+        self.num_susceptible + self.num_exposed + self.num_infectious + self.num_recovered + self.num_dead
+    }
+
+    /// The new infections since the previous counts of the same agents, see `StratifiedCounts`
+    #[inline]
+    pub fn incidence_since(&self, previous: &StateCounts) -> usize {
+        previous.num_susceptible.saturating_sub(self.num_susceptible)
+    }
+}
+
+/// A description of a simulation
+#[derive(Serialize)]
+struct ConcludingMetric {
+    total_time_steps: TimeStep,
+    simulation_execution_time_in_secs: f64,
+    synthetic_environment_path: PathBuf,
+    provenance: Provenance,
+    summary: EpidemicSummaryMetric,
+}
+
+/// What's needed to tell which run of which version of the simulation produced a report
+#[derive(Serialize)]
+struct Provenance {
+    iteration: usize,
+    run_id: String,
+    crate_version: &'static str,
+    /// The commit the simulation was built from, suffixed with "-dirty" if there were uncommitted
+    /// changes, see build.rs
+    git_hash: &'static str,
+    /// The seed every random draw was made from, which reproduces the run along with the parameters
+    seed: Option<u64>,
+    global_params: Option<GlobalSimParams>,
+    hospitalisation_params: Option<HospitalisationParams>,
+}
+
+/// Writes the parameters of the simulation and creates the intermediary report in the run's
+/// report directory
+#[inline]
+pub fn intialise_reporting_files(report_dir: &ReportDir, format: ReportFormat) -> Result<ReportWriter<IntermediaryMetric>, Box<dyn Error>> {
+    let param_path = report_dir.report_path("parameters", "json")?;
+    println!("Creating parameters file: {}", param_path.display());
+    write_json_atomically(&param_path, &GLOBAL_PARAMS.get())?;
+    ReportWriter::create(report_dir, "intermediary", format)
+}
+
+/// Creates the report of each day's testing and contact tracing
+#[inline]
+pub fn create_testing_report_file(report_dir: &ReportDir) -> Result<ReportWriter<TestingMetric>, Box<dyn Error>> {
+    ReportWriter::create(report_dir, "testing", ReportFormat::Csv)
+}
+
+/// Creates the report of each hospital's occupancy over time
+#[inline]
+pub fn create_hospital_report_file(report_dir: &ReportDir) -> Result<ReportWriter<HospitalMetric>, Box<dyn Error>> {
+    ReportWriter::create(report_dir, "hospitals", ReportFormat::Csv)
+}
+
+/// Creates the log of every new infection and who or where it's attributed to
+#[inline]
+pub fn create_transmission_report_file(report_dir: &ReportDir, format: ReportFormat) -> Result<ReportWriter<Transmission>, Box<dyn Error>> {
+    ReportWriter::create(report_dir, "transmissions", format)
+}
+
+/// Creates the report of each age band's incidence, prevalence and outcomes, see `AgeReport`
+#[inline]
+pub fn create_age_report_file(report_dir: &ReportDir, format: ReportFormat) -> Result<ReportWriter<AgeMetric>, Box<dyn Error>> {
+    ReportWriter::create(report_dir, "ages", format)
+}
+
+/// Creates the report of each area's disease states over time, see `SpatialReport`
+#[inline]
+pub fn create_spatial_report_file(report_dir: &ReportDir, format: ReportFormat) -> Result<ReportWriter<SpatialMetric>, Box<dyn Error>> {
+    ReportWriter::create(report_dir, "spatial", format)
+}
+
+/// Writes the reproduction numbers of every day so far to `rt.csv`
+pub fn write_reproduction_numbers(report_dir: &ReportDir, reproduction_numbers: &ReproductionNumbers) -> Result<(), Box<dyn Error>> {
+    let mut report_writer = ReportWriter::create(report_dir, "rt", ReportFormat::Csv)?;
+    for metric in reproduction_numbers.metrics() {
+        report_writer.write(metric)?;
+    }
+    report_writer.finish()
+}
+
+/// A hospital's occupancy at a time step
+#[derive(Serialize, Deserialize)]
+pub struct HospitalMetric {
+    time_step: TimeStep,
+    container_idx: u64,
+    general_beds: u32,
+    general_occupied: u32,
+    icu_beds: u32,
+    icu_occupied: u32,
+    over_capacity: u32,
+}
+
+impl HospitalMetric {
+    fn new(time_step: TimeStep, hospital: &Hospital) -> Self {
+        HospitalMetric {
+            time_step,
+            container_idx: hospital.container_idx,
+            general_beds: hospital.general_beds,
+            general_occupied: hospital.general_occupied,
+            icu_beds: hospital.icu_beds,
+            icu_occupied: hospital.icu_occupied,
+            over_capacity: hospital.over_capacity,
+        }
+    }
+}
+
+/// Writes the occupancy of every hospital, a row for each
+#[inline]
+pub fn write_hospital_occupancy(report_writer: &mut ReportWriter<HospitalMetric>, time_step: TimeStep, hospitalisation: &Hospitalisation)
+                                -> Result<(), Box<dyn Error>> {
+    for hospital in hospitalisation.hospitals() {
+        report_writer.write(HospitalMetric::new(time_step, hospital))?;
+    }
+    Ok(())
+}
+
+#[inline]
+pub fn write_testing_metric(report_writer: &mut ReportWriter<TestingMetric>, metric: TestingMetric) -> Result<(), Box<dyn Error>> {
+    report_writer.write(metric)
+}
+
+#[inline]
+pub fn write_intermediary_metric(report_writer: &mut ReportWriter<IntermediaryMetric>, time_step: TimeStep, agents: &Agents,
+                                 interventions: &Interventions, hospitalisation: Option<&Hospitalisation>) -> Result<(), Box<dyn Error>> {
+    let counts = StateCounts::from_agents(agents);
+    let new_infections = agents.transmission_log.new_infections();
+
+    let metric = IntermediaryMetric {
+        time_step,
+        num_susceptible: counts.num_susceptible,
+        num_exposed: counts.num_exposed,
+        num_infectious: counts.num_infectious,
+        num_recovered: counts.num_recovered,
+        num_dead: counts.num_dead,
+        num_hospitalised: hospitalisation.map_or(0, |hospitalisation| hospitalisation.num_hospitalised()),
+        num_staying_home: interventions.num_staying_home(),
+        new_infections_household: new_infections.household,
+        new_infections_workplace: new_infections.workplace,
+        new_infections_school: new_infections.school,
+        new_infections_hospital: new_infections.hospital,
+        new_infections_seeded: new_infections.seeded,
+        new_infections_imported: new_infections.imported,
+        active_interventions: interventions.describe_active(),
+    };
+
+    report_writer.write(metric)
+}
+
+/// Writes `concluding.json` to the run's report directory
+#[inline]
+pub fn write_concluding_metrics(report_dir: &ReportDir, time_step: TimeStep, exec_time: Duration, synthetic_environment_path: PathBuf,
+                                iteration: usize, summary: EpidemicSummaryMetric, hospitalisation: Option<&Hospitalisation>)
+                                -> Result<(), Box<dyn Error>> {
+    let metric = ConcludingMetric {
+        total_time_steps: time_step,
+        simulation_execution_time_in_secs: exec_time.as_secs_f64(),
+        synthetic_environment_path: synthetic_environment_path.canonicalize()?,
+        provenance: Provenance {
+            iteration,
+            run_id: report_dir.run_id().to_owned(),
+            crate_version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("OUTBREAK_SIM_GIT_HASH"),
+            seed: GLOBAL_PARAMS.get().map(|params| params.seed),
+            global_params: GLOBAL_PARAMS.get().cloned(),
+            hospitalisation_params: hospitalisation.map(|hospitalisation| hospitalisation.params().clone()),
+        },
+        summary,
+    };
+    let report_path = report_dir.report_path("concluding", "json")?;
+    println!("Writing concluding report file: {}", report_path.display());
+    write_json_atomically(&report_path, &metric)
+}
+
+#[inline]
+pub fn write_transmissions(report_writer: &mut ReportWriter<Transmission>, transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+    for &transmission in transmissions {
+        report_writer.write(transmission)?;
+    }
+    Ok(())
+}
+
+/// Appends entries of the trajectory log to a CSV file, writing the header if the file is new
+pub fn write_trajectory_log<P>(path: P, entries: &[TrajectoryEntry]) -> Result<(), Box<dyn Error>>
+    where P: AsRef<Path>
+{
+    let write_header = !path.as_ref().exists();
+    let file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    let mut writer = csv::WriterBuilder::new().has_headers(write_header).from_writer(file);
+    for entry in entries {
+        writer.serialize(entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
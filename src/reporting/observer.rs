@@ -0,0 +1,358 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Instant;
+
+use crate::{Sim, SimError};
+use crate::disease::{MixingStrategy, Transmission};
+use crate::interventions::TestingMetric;
+use crate::reporting::{AgeBands, AgeMetric, AgeReport, container_snapshots, ContainerSnapshot, EpidemicSummary, HospitalMetric,
+                       IntermediaryMetric, ReportDir, ReportFormat, ReportWriter, ReproductionNumbers, SnapshotFormat, snapshots_to_geojson,
+                       SnapshotTimes, SpatialMetric, SpatialReport, write_concluding_metrics, write_hospital_occupancy,
+                       write_intermediary_metric, write_json_atomically, write_reproduction_numbers, write_testing_metric,
+                       write_transmissions};
+use crate::shared::get_time_steps_per_day;
+use crate::shared::types::TimeStep;
+
+/// # Sim Observer
+/// Something that's shown the simulation's state as it runs, e.g. to write a report, registered
+/// with `Sim::add_observer`.
+///
+/// Observers only implement the methods for the intervals they're interested in, how often they're
+/// called being chosen when the observer is registered, see `ObserverInterval`:
+/// - `on_time_step` after a time step the interval includes
+/// - `on_day` after the first time step of a day, if the interval includes it, with the infections
+///   since it was last called drained from the transmission log, empty unless it's recording
+/// - `on_end` from `Sim::finish`, with the infections since `on_day` was last called
+///
+/// An observer failing stops the simulation, the error being returned from `Sim::update` or
+/// `Sim::finish`.
+pub trait SimObserver<M: MixingStrategy> {
+    fn on_time_step(&mut self, _sim: &Sim<M>, _time_step: TimeStep) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_day(&mut self, _sim: &Sim<M>, _day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+/// How often an observer is shown the simulation, given when it's registered, both `on_time_step`
+/// and `on_day` only being called at the time steps the interval includes. Intervals of 0 are
+/// treated as 1.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ObserverInterval {
+    /// Every this many time steps, from the first, so `on_day` is only called on the days whose
+    /// first time step is one of them
+    TimeSteps(u32),
+    /// The first time step of every this many days, from the first
+    Days(u32),
+}
+
+impl Default for ObserverInterval {
+    fn default() -> Self {
+        ObserverInterval::TimeSteps(1)
+    }
+}
+
+impl ObserverInterval {
+    #[inline]
+    pub fn includes_time_step(&self, time_step: TimeStep) -> bool {
+        match *self {
+            ObserverInterval::TimeSteps(time_steps) => { time_step.is_multiple_of(time_steps.max(1)) }
+            ObserverInterval::Days(days) => { time_step.is_multiple_of(days.max(1) * get_time_steps_per_day()) }
+        }
+    }
+
+    /// Whether the first time step of the day is included
+    #[inline]
+    pub fn includes_day(&self, day: u32) -> bool {
+        self.includes_time_step(day * get_time_steps_per_day())
+    }
+}
+
+/// An observer along with the infections it's yet to be given, for those not shown every day
+struct RegisteredObserver<M: MixingStrategy> {
+    observer: Box<dyn SimObserver<M> + Send + Sync>,
+    interval: ObserverInterval,
+    transmissions: Vec<Transmission>,
+}
+
+impl<M: MixingStrategy> RegisteredObserver<M> {
+    /// The infections since the observer was last given any, including the given ones
+    fn take_transmissions<'a>(&mut self, transmissions: &'a [Transmission]) -> Cow<'a, [Transmission]> {
+        if self.transmissions.is_empty() {
+            transmissions.into()
+        } else {
+            self.transmissions.extend_from_slice(transmissions);
+            std::mem::take(&mut self.transmissions).into()
+        }
+    }
+}
+
+/// The observers registered with a `Sim`, which aren't cloned along with it as they usually write
+/// to a file, a clone of a sim having none
+pub struct Observers<M: MixingStrategy> {
+    observers: Vec<RegisteredObserver<M>>,
+}
+
+impl<M: MixingStrategy> Default for Observers<M> {
+    fn default() -> Self {
+        Observers { observers: Vec::new() }
+    }
+}
+
+impl<M: MixingStrategy> Clone for Observers<M> {
+    /// An empty set of observers, see `Observers`
+    fn clone(&self) -> Self {
+        Observers::default()
+    }
+}
+
+impl<M: MixingStrategy> Observers<M> {
+    #[inline]
+    pub fn push(&mut self, observer: Box<dyn SimObserver<M> + Send + Sync>, interval: ObserverInterval) {
+        self.observers.push(RegisteredObserver { observer, interval, transmissions: Vec::new() });
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+
+    pub fn on_time_step(&mut self, sim: &Sim<M>, time_step: TimeStep) -> Result<(), SimError> {
+        self.observers.iter_mut()
+            .filter(|registered| registered.interval.includes_time_step(time_step))
+            .try_for_each(|registered| registered.observer.on_time_step(sim, time_step))
+            .map_err(SimError::Observer)
+    }
+
+    pub fn on_day(&mut self, sim: &Sim<M>, day: u32, transmissions: &[Transmission]) -> Result<(), SimError> {
+        self.observers.iter_mut().try_for_each(|registered| {
+            if registered.interval.includes_day(day) {
+                let transmissions = registered.take_transmissions(transmissions);
+                registered.observer.on_day(sim, day, &transmissions)
+            } else {
+                registered.transmissions.extend_from_slice(transmissions);
+                Ok(())
+            }
+        }).map_err(SimError::Observer)
+    }
+
+    pub fn on_end(&mut self, sim: &Sim<M>, time_step: TimeStep, transmissions: &[Transmission]) -> Result<(), SimError> {
+        self.observers.iter_mut().try_for_each(|registered| {
+            let transmissions = registered.take_transmissions(transmissions);
+            registered.observer.on_end(sim, time_step, &transmissions)
+        }).map_err(SimError::Observer)
+    }
+}
+
+/// Writes the intermediary report every time step
+pub struct IntermediaryReporter {
+    pub writer: ReportWriter<IntermediaryMetric>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for IntermediaryReporter {
+    fn on_time_step(&mut self, sim: &Sim<M>, time_step: TimeStep) -> Result<(), Box<dyn Error>> {
+        write_intermediary_metric(&mut self.writer, time_step, &sim.agents, &sim.interventions, sim.hospitalisation.as_ref())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()
+    }
+}
+
+/// Writes the testing report every day, if the scenario has testing
+pub struct TestingReporter {
+    pub writer: ReportWriter<TestingMetric>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for TestingReporter {
+    fn on_day(&mut self, sim: &Sim<M>, _day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        if let Some(testing) = sim.interventions.testing() {
+            write_testing_metric(&mut self.writer, testing.metric())?;
+        }
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()
+    }
+}
+
+/// Writes the occupancy of every hospital every day, if there's hospitalisation
+pub struct HospitalReporter {
+    pub writer: ReportWriter<HospitalMetric>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for HospitalReporter {
+    fn on_day(&mut self, sim: &Sim<M>, day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        if let Some(hospitalisation) = sim.hospitalisation.as_ref() {
+            write_hospital_occupancy(&mut self.writer, day * get_time_steps_per_day(), hospitalisation)?;
+        }
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()
+    }
+}
+
+/// Writes the transmission log every day, which the `Sim` must be recording
+pub struct TransmissionReporter {
+    pub writer: ReportWriter<Transmission>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for TransmissionReporter {
+    fn on_day(&mut self, _sim: &Sim<M>, _day: u32, transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        write_transmissions(&mut self.writer, transmissions)
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        write_transmissions(&mut self.writer, transmissions)?;
+        self.writer.finish()
+    }
+}
+
+/// Writes the spatial report every day
+pub struct SpatialReporter {
+    pub report: SpatialReport,
+    pub writer: ReportWriter<SpatialMetric>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for SpatialReporter {
+    fn on_day(&mut self, sim: &Sim<M>, day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.report.write(&mut self.writer, day, &sim.agents)
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()
+    }
+}
+
+/// Writes the age report every day
+pub struct AgeReporter {
+    pub report: AgeReport,
+    pub writer: ReportWriter<AgeMetric>,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for AgeReporter {
+    fn on_day(&mut self, sim: &Sim<M>, day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.report.write(&mut self.writer, day, &sim.agents, sim.hospitalisation.as_ref())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.writer.finish()
+    }
+}
+
+/// Estimates the reproduction numbers from the transmission log, which the `Sim` must be
+/// recording, writing them when the simulation ends
+pub struct ReproductionReporter {
+    pub reproduction_numbers: ReproductionNumbers,
+    pub report_dir: ReportDir,
+}
+
+impl<M: MixingStrategy> SimObserver<M> for ReproductionReporter {
+    fn on_day(&mut self, _sim: &Sim<M>, _day: u32, transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.reproduction_numbers.record(transmissions);
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.reproduction_numbers.record(transmissions);
+        write_reproduction_numbers(&self.report_dir, &self.reproduction_numbers)
+    }
+}
+
+/// Snapshots every container at the chosen time steps, to `containers.csv` or to
+/// `containers_<time step>.geojson`
+pub struct ContainerSnapshotReporter {
+    report_dir: ReportDir,
+    times: SnapshotTimes,
+    /// `None` when writing GeoJSON
+    csv_writer: Option<ReportWriter<ContainerSnapshot>>,
+}
+
+impl ContainerSnapshotReporter {
+    pub fn new(report_dir: ReportDir, format: SnapshotFormat, times: SnapshotTimes) -> Result<Self, Box<dyn Error>> {
+        let csv_writer = match format {
+            SnapshotFormat::Csv => { Some(ReportWriter::create(&report_dir, "containers", ReportFormat::Csv)?) }
+            SnapshotFormat::GeoJson => { None }
+        };
+        Ok(ContainerSnapshotReporter { report_dir, times, csv_writer })
+    }
+}
+
+impl<M: MixingStrategy> SimObserver<M> for ContainerSnapshotReporter {
+    fn on_time_step(&mut self, sim: &Sim<M>, time_step: TimeStep) -> Result<(), Box<dyn Error>> {
+        if !self.times.includes(time_step) {
+            return Ok(());
+        }
+        let snapshots = container_snapshots(time_step, &sim.agents, &sim.containers);
+        match self.csv_writer.as_mut() {
+            Some(writer) => {
+                for snapshot in snapshots {
+                    writer.write(snapshot)?;
+                }
+            }
+            None => {
+                let path = self.report_dir.report_path(&format!("containers_{}", time_step), "geojson")?;
+                write_json_atomically(&path, &snapshots_to_geojson(&snapshots)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<M>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        match self.csv_writer.as_mut() {
+            Some(writer) => { writer.finish() }
+            None => { Ok(()) }
+        }
+    }
+}
+
+/// Summarises the epidemic and writes the concluding report when the simulation ends, timing the
+/// execution from when it's created
+pub struct ConcludingReporter {
+    pub report_dir: ReportDir,
+    pub synthetic_environment_path: PathBuf,
+    pub iteration: usize,
+    pub summary: EpidemicSummary,
+    pub start_time: Instant,
+}
+
+impl ConcludingReporter {
+    pub fn new(report_dir: ReportDir, synthetic_environment_path: PathBuf, iteration: usize, age_bands: AgeBands) -> Self {
+        ConcludingReporter {
+            report_dir,
+            synthetic_environment_path,
+            iteration,
+            summary: EpidemicSummary::new(age_bands),
+            start_time: Instant::now(),
+        }
+    }
+}
+
+impl<M: MixingStrategy> SimObserver<M> for ConcludingReporter {
+    fn on_time_step(&mut self, sim: &Sim<M>, time_step: TimeStep) -> Result<(), Box<dyn Error>> {
+        self.summary.update(time_step, &sim.agents);
+        Ok(())
+    }
+
+    fn on_end(&mut self, sim: &Sim<M>, time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        let summary = self.summary.metric(&sim.agents, sim.hospitalisation.as_ref());
+        write_concluding_metrics(&self.report_dir, time_step, Instant::now().duration_since(self.start_time),
+                                 self.synthetic_environment_path.clone(), self.iteration, summary, sim.hospitalisation.as_ref())?;
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+use std::error::Error;
+use std::ffi::OsString;
+use std::fs;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+use csv::Writer;
+use serde::Serialize;
+
+#[cfg(feature = "columnar")]
+use crate::reporting::{ColumnarFormat, ColumnarRow, ColumnarWriter, DEFAULT_BATCH_SIZE};
+
+/// The file format of the reports with a row per time step, day, or event, the others always
+/// being written as CSV
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum ReportFormat {
+    #[default]
+    Csv,
+    /// Requires the `columnar` feature
+    Parquet,
+    /// Requires the `columnar` feature
+    ArrowIpc,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => { Ok(ReportFormat::Csv) }
+            "parquet" => { Ok(ReportFormat::Parquet) }
+            "arrow" => { Ok(ReportFormat::ArrowIpc) }
+            _ => { Err(format!("unknown report format {}, expected csv, parquet, or arrow", s)) }
+        }
+    }
+}
+
+/// # Report Directory
+/// The directory a run's reports are written to, `<root>/<model name>/<run id>`.
+///
+/// The directory is claimed when it's created, so concurrent runs given the same run ID each get
+/// their own, the later ones having `-1`, `-2`, ... appended to it. Unless replacing, existing
+/// reports are never overwritten.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportDir {
+    path: PathBuf,
+    run_id: String,
+    replace: bool,
+}
+
+impl ReportDir {
+    /// Creates a directory for the run, or reuses the one with the given run ID if replacing its
+    /// reports
+    pub fn create<P>(root: P, model_name: &str, run_id: &str, replace: bool) -> Result<Self, Box<dyn Error>>
+        where P: Into<PathBuf>
+    {
+        for name in [model_name, run_id].iter() {
+            let mut components = Path::new(name).components();
+            if !matches!((components.next(), components.next()), (Some(Component::Normal(_)), None)) {
+                return Err(format!("{:?} can't be used as the name of a report directory", name).into());
+            }
+        }
+        let mut model_dir = root.into();
+        model_dir.push(model_name);
+        fs::create_dir_all(&model_dir)?;
+
+        let mut suffix = 0;
+        loop {
+            let unique_run_id = if suffix == 0 { run_id.to_owned() } else { format!("{}-{}", run_id, suffix) };
+            let path = model_dir.join(&unique_run_id);
+            match fs::create_dir(&path) {
+                Ok(()) => { return Ok(ReportDir { path, run_id: unique_run_id, replace }); }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if replace && suffix == 0 && path.is_dir() {
+                        return Ok(ReportDir { path, run_id: unique_run_id, replace });
+                    }
+                }
+                Err(e) => { return Err(e.into()); }
+            }
+            suffix += 1;
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The run ID the directory is named after, which may have had a suffix added to make it unique
+    #[inline]
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// The path of a report in the directory, failing if it already exists and isn't being replaced
+    pub fn report_path(&self, name: &str, extension: &str) -> Result<PathBuf, Box<dyn Error>> {
+        let mut report_path = self.path.join(name);
+        report_path.set_extension(extension);
+        if !self.replace && report_path.exists() {
+            Err(format!("report {} already exists", report_path.display()).into())
+        } else {
+            Ok(report_path)
+        }
+    }
+}
+
+/// The path a report is written to until it's complete, after which it's renamed to its own path
+fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().map(OsString::from).unwrap_or_default();
+    file_name.push(".partial");
+    path.with_file_name(file_name)
+}
+
+/// Writes a value as JSON to a partial file, renaming it into place once it's complete
+pub fn write_json_atomically<V: Serialize>(path: &Path, value: &V) -> Result<(), Box<dyn Error>> {
+    let partial_path = partial_path(path);
+    serde_json::to_writer_pretty(&File::create(&partial_path)?, value)?;
+    fs::rename(partial_path, path)?;
+    Ok(())
+}
+
+/// A row of a report, which must also have a fixed columnar schema when built with the `columnar`
+/// feature, see `ColumnarRow`
+#[cfg(feature = "columnar")]
+pub trait ReportRow: ColumnarRow {}
+
+#[cfg(feature = "columnar")]
+impl<T: ColumnarRow> ReportRow for T {}
+
+/// A row of a report, which must also have a fixed columnar schema when built with the `columnar`
+/// feature, see `ColumnarRow`
+#[cfg(not(feature = "columnar"))]
+pub trait ReportRow: Serialize {}
+
+#[cfg(not(feature = "columnar"))]
+impl<T: Serialize> ReportRow for T {}
+
+// there's only one sink per report, so its size doesn't matter
+#[allow(clippy::large_enum_variant)]
+enum ReportSink<T> {
+    Csv(Writer<File>),
+    #[cfg(feature = "columnar")]
+    Columnar(ColumnarWriter<T>),
+    #[cfg(not(feature = "columnar"))]
+    _Unused(std::marker::PhantomData<T>),
+}
+
+/// # Report Writer
+/// A report written either as CSV, flushing every row, or in a columnar format, in batches.
+///
+/// Rows are written to `<report>.partial` until `finish` is called, when it's renamed to the
+/// report's path, so a report that exists is always complete.
+pub struct ReportWriter<T> {
+    sink: Option<ReportSink<T>>,
+    path: PathBuf,
+}
+
+impl<T: ReportRow> ReportWriter<T> {
+    /// Creates a report in the directory, with an extension for its format
+    pub fn create(report_dir: &ReportDir, name: &str, format: ReportFormat) -> Result<Self, Box<dyn Error>> {
+        let path;
+        let sink = match format {
+            ReportFormat::Csv => {
+                path = report_dir.report_path(name, "csv")?;
+                ReportSink::Csv(Writer::from_path(partial_path(&path))?)
+            }
+            #[cfg(feature = "columnar")]
+            ReportFormat::Parquet | ReportFormat::ArrowIpc => {
+                let format = if format == ReportFormat::Parquet { ColumnarFormat::Parquet } else { ColumnarFormat::ArrowIpc };
+                path = report_dir.report_path(name, format.extension())?;
+                ReportSink::Columnar(ColumnarWriter::new(partial_path(&path), format, DEFAULT_BATCH_SIZE))
+            }
+            #[cfg(not(feature = "columnar"))]
+            ReportFormat::Parquet | ReportFormat::ArrowIpc => {
+                return Err(format!("{:?} reports need outbreak-sim to be built with the columnar feature", format).into());
+            }
+        };
+        println!("Creating {} report file: {}", name, path.display());
+        Ok(ReportWriter { sink: Some(sink), path })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn write(&mut self, row: T) -> Result<(), Box<dyn Error>> {
+        match self.sink.as_mut() {
+            Some(ReportSink::Csv(writer)) => {
+                writer.serialize(row)?;
+                writer.flush()?;
+            }
+            #[cfg(feature = "columnar")]
+            Some(ReportSink::Columnar(writer)) => { writer.write(row)?; }
+            #[cfg(not(feature = "columnar"))]
+            Some(ReportSink::_Unused(_)) => { unreachable!() }
+            None => { return Err(format!("report {} has already been finished", self.path.display()).into()); }
+        }
+        Ok(())
+    }
+
+    /// Writes anything buffered and renames the report into place, after which nothing else can be
+    /// written. Finishing more than once does nothing.
+    pub fn finish(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.sink.take() {
+            Some(ReportSink::Csv(mut writer)) => { writer.flush()?; }
+            #[cfg(feature = "columnar")]
+            Some(ReportSink::Columnar(mut writer)) => { writer.finish()?; }
+            #[cfg(not(feature = "columnar"))]
+            Some(ReportSink::_Unused(_)) => { unreachable!() }
+            None => { return Ok(()); }
+        }
+        // columnar reports without any rows never create a file
+        let partial_path = partial_path(&self.path);
+        if partial_path.exists() {
+            fs::rename(partial_path, &self.path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interventions::TestingMetric;
+
+    use super::*;
+
+    #[test]
+    fn test_report_dirs_are_unique() {
+        let root = std::env::temp_dir().join(format!("outbreak_sim_report_dirs_{}", std::process::id()));
+        let first = ReportDir::create(&root, "model", "0", false).unwrap();
+        let second = ReportDir::create(&root, "model", "0", false).unwrap();
+        let replacing = ReportDir::create(&root, "model", "0", true).unwrap();
+        assert_eq!(first.run_id(), "0");
+        assert_eq!(second.run_id(), "0-1");
+        assert_eq!(replacing.path(), first.path());
+        assert!(ReportDir::create(&root, "model", "../0", false).is_err());
+
+        let mut writer = ReportWriter::create(&first, "rows", ReportFormat::Csv).unwrap();
+        writer.write(TestingMetric::default()).unwrap();
+        assert!(!writer.path().exists());
+        writer.finish().unwrap();
+        assert!(writer.path().exists());
+        assert!(first.report_path("rows", "csv").is_err());
+
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,183 @@
+use serde::{Deserialize, Serialize};
+
+use crate::disease::{InfectionSource, Transmission};
+use crate::shared::get_time_steps_per_day;
+
+/// The number of days the instantaneous reproduction number is smoothed over
+const DEFAULT_WINDOW_DAYS: usize = 7;
+
+/// The reproduction numbers of a day, as written to the R_t report
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct ReproductionMetric {
+    pub day: u32,
+    /// The infections on the day, including those seeded and imported
+    pub incidence: usize,
+    /// The mean number of agents infected by those infected on the day, `None` if nobody was
+    pub case_reproduction_number: Option<f32>,
+    /// The infections over the window ending on the day relative to the infectiousness of those
+    /// infected previously, `None` until anyone was infected
+    pub instantaneous_reproduction_number: Option<f32>,
+}
+
+/// # Reproduction Numbers
+/// Estimates the reproduction numbers over time from the transmission log.
+///
+/// The case reproduction number is the mean number of secondary infections of the agents infected
+/// each day. It's right-censored, those infected recently not yet having infected everyone they
+/// will.
+///
+/// The instantaneous reproduction number is estimated from incidence as in Cori et al. (2013),
+/// weighting past infections by the generation interval distribution, which is taken from the
+/// infector-infectee pairs of the log itself.
+#[derive(Clone)]
+pub struct ReproductionNumbers {
+    /// The day each agent was infected, if they have been
+    infection_day: Vec<Option<u32>>,
+    incidence: Vec<usize>,
+    /// The infections within containers each day, excluding those seeded and imported
+    local_incidence: Vec<usize>,
+    /// The infections caused by those infected on each day
+    secondary_infections: Vec<usize>,
+    /// The number of infector-infectee pairs for each number of days between their infections
+    generation_intervals: Vec<usize>,
+    window_days: usize,
+}
+
+impl ReproductionNumbers {
+    pub fn new(num_agents: u32) -> Self {
+        ReproductionNumbers {
+            infection_day: vec![None; num_agents as usize],
+            incidence: Vec::new(),
+            local_incidence: Vec::new(),
+            secondary_infections: Vec::new(),
+            generation_intervals: Vec::new(),
+            window_days: DEFAULT_WINDOW_DAYS,
+        }
+    }
+
+    /// The number of days the instantaneous reproduction number is smoothed over
+    pub fn window_days(mut self, window_days: usize) -> Self {
+        self.window_days = window_days.max(1);
+        self
+    }
+
+    /// Adds entries from the transmission log, in the order they were recorded
+    pub fn record(&mut self, transmissions: &[Transmission]) {
+        for transmission in transmissions {
+            let day = transmission.time_step / get_time_steps_per_day();
+            self.infection_day[transmission.infectee as usize] = Some(day);
+            add_at(&mut self.incidence, day as usize, 1);
+            if transmission.source == InfectionSource::Container {
+                add_at(&mut self.local_incidence, day as usize, 1);
+            }
+            let infector_day = transmission.infector.and_then(|infector| self.infection_day[infector as usize]);
+            if let Some(infector_day) = infector_day {
+                add_at(&mut self.secondary_infections, infector_day as usize, 1);
+                add_at(&mut self.generation_intervals, day.saturating_sub(infector_day) as usize, 1);
+            }
+        }
+    }
+
+    /// The mean secondary infections of those infected on each day
+    pub fn case_reproduction_number(&self) -> Vec<Option<f32>> {
+        (0..self.incidence.len()).map(|day| {
+            match self.incidence[day] {
+                0 => { None }
+                incidence => { Some(*self.secondary_infections.get(day).unwrap_or(&0) as f32 / incidence as f32) }
+            }
+        }).collect()
+    }
+
+    /// The instantaneous reproduction number of each day, over the window of days ending on it
+    pub fn instantaneous_reproduction_number(&self) -> Vec<Option<f32>> {
+        let weights = self.generation_interval_weights();
+        // the expected infections on each day if every infected agent infected one other
+        let infectiousness: Vec<f32> = (0..self.incidence.len()).map(|day| {
+            (1..weights.len().min(day + 1)).map(|interval| self.incidence[day - interval] as f32 * weights[interval]).sum()
+        }).collect();
+
+        (0..self.incidence.len()).map(|day| {
+            let window = day.saturating_sub(self.window_days - 1)..=day;
+            let expected: f32 = infectiousness[window.clone()].iter().sum();
+            let observed: usize = window.map(|day| *self.local_incidence.get(day).unwrap_or(&0)).sum();
+            if expected > 0.0 { Some(observed as f32 / expected) } else { None }
+        }).collect()
+    }
+
+    /// The reproduction numbers of every day with infections so far
+    pub fn metrics(&self) -> Vec<ReproductionMetric> {
+        self.case_reproduction_number().into_iter()
+            .zip(self.instantaneous_reproduction_number())
+            .enumerate()
+            .map(|(day, (case_reproduction_number, instantaneous_reproduction_number))| ReproductionMetric {
+                day: day as u32,
+                incidence: self.incidence[day],
+                case_reproduction_number,
+                instantaneous_reproduction_number,
+            }).collect()
+    }
+
+    /// The share of infections at each generation interval in days, infections on the same day as
+    /// their infector being excluded as they'd be counted towards their own day's infectiousness
+    fn generation_interval_weights(&self) -> Vec<f32> {
+        let total: usize = self.generation_intervals.iter().skip(1).sum();
+        if total == 0 {
+            return Vec::new();
+        }
+        self.generation_intervals.iter().enumerate()
+            .map(|(interval, &count)| if interval == 0 { 0.0 } else { count as f32 / total as f32 })
+            .collect()
+    }
+}
+
+#[inline]
+fn add_at(counts: &mut Vec<usize>, idx: usize, amount: usize) {
+    if counts.len() <= idx {
+        counts.resize(idx + 1, 0);
+    }
+    counts[idx] += amount;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+
+    use super::*;
+
+    fn transmission(day: u32, infectee: u32, infector: Option<u32>) -> Transmission {
+        let time_step = day * get_time_steps_per_day();
+        match infector {
+            Some(infector) => {
+                Transmission { infector: Some(infector), source: InfectionSource::Container,
+                    ..Transmission::external(time_step, infectee, InfectionSource::Container) }
+            }
+            None => { Transmission::external(time_step, infectee, InfectionSource::Seeded) }
+        }
+    }
+
+    #[test]
+    fn test_reproduction_numbers_of_doubling_chain() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        // every agent infects two others the day after they're infected
+        let mut transmissions = vec![transmission(0, 0, None)];
+        let mut next_agent = 1;
+        for day in 1..4 {
+            let infectors: Vec<u32> = transmissions.iter()
+                .filter(|transmission| transmission.time_step == (day - 1) * get_time_steps_per_day())
+                .map(|transmission| transmission.infectee)
+                .collect();
+            for infector in infectors {
+                for _ in 0..2 {
+                    transmissions.push(transmission(day, next_agent, Some(infector)));
+                    next_agent += 1;
+                }
+            }
+        }
+
+        let mut reproduction_numbers = ReproductionNumbers::new(next_agent).window_days(1);
+        reproduction_numbers.record(&transmissions);
+
+        assert_eq!(reproduction_numbers.case_reproduction_number(), vec![Some(2.0), Some(2.0), Some(2.0), Some(0.0)]);
+        assert_eq!(reproduction_numbers.instantaneous_reproduction_number(), vec![None, Some(2.0), Some(2.0), Some(2.0)]);
+    }
+}
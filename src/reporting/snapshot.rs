@@ -0,0 +1,140 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::agents::Agents;
+use crate::containers::{Containers, ContainerType};
+use crate::disease::MixingStrategy;
+use crate::reporting::StateCounts;
+use crate::shared::types::TimeStep;
+
+/// The state of a container at a time step, as written to the container snapshots
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ContainerSnapshot {
+    pub time_step: TimeStep,
+    pub container_idx: u64,
+    pub container_type: ContainerType,
+    /// The container's position, in the model's coordinates
+    pub x: f32,
+    pub y: f32,
+    /// The agents in the container, which excludes those travelling to or from it
+    pub occupancy: usize,
+    pub num_susceptible: usize,
+    pub num_exposed: usize,
+    pub num_infectious: usize,
+    pub num_recovered: usize,
+    pub num_dead: usize,
+}
+
+/// Snapshots every container, including those that are empty
+pub fn container_snapshots<M>(time_step: TimeStep, agents: &Agents, containers: &Containers<M>) -> Vec<ContainerSnapshot>
+    where M: MixingStrategy
+{
+    containers.iter().enumerate().map(|(container_idx, container)| {
+        let mut counts = StateCounts::default();
+        for &agent_idx in container.inhabitants.iter() {
+            counts.add(agents.disease_statuses[agent_idx as usize].state);
+        }
+        ContainerSnapshot {
+            time_step,
+            container_idx: container_idx as u64,
+            container_type: container.container_type,
+            x: container.pos.x(),
+            y: container.pos.y(),
+            occupancy: container.inhabitants.len(),
+            num_susceptible: counts.num_susceptible,
+            num_exposed: counts.num_exposed,
+            num_infectious: counts.num_infectious,
+            num_recovered: counts.num_recovered,
+            num_dead: counts.num_dead,
+        }
+    }).collect()
+}
+
+/// A GeoJSON feature collection of the snapshots, each a point with the snapshot as its properties.
+/// The coordinates are the model's own rather than longitude and latitude, so GIS tools need to be
+/// told the model's coordinate reference system.
+pub fn snapshots_to_geojson(snapshots: &[ContainerSnapshot]) -> Result<Value, serde_json::Error> {
+    let features = snapshots.iter().map(|snapshot| {
+        Ok(json!({
+            "type": "Feature",
+            "geometry": { "type": "Point", "coordinates": [snapshot.x, snapshot.y] },
+            "properties": serde_json::to_value(snapshot)?,
+        }))
+    }).collect::<Result<Vec<Value>, serde_json::Error>>()?;
+    Ok(json!({ "type": "FeatureCollection", "features": features }))
+}
+
+/// How container snapshots are written, CSV being a single report with a row per container per
+/// snapshot, and GeoJSON being a file per snapshot
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub enum SnapshotFormat {
+    #[default]
+    Csv,
+    GeoJson,
+}
+
+impl FromStr for SnapshotFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => { Ok(SnapshotFormat::Csv) }
+            "geojson" => { Ok(SnapshotFormat::GeoJson) }
+            _ => { Err(format!("unknown snapshot format {}, expected csv or geojson", s)) }
+        }
+    }
+}
+
+/// The time steps to snapshot the containers at
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotTimes {
+    pub time_steps: Vec<TimeStep>,
+    /// Also snapshot every this many time steps, from the first
+    pub every: Option<TimeStep>,
+}
+
+impl SnapshotTimes {
+    #[inline]
+    pub fn includes(&self, time_step: TimeStep) -> bool {
+        self.time_steps.contains(&time_step) || self.every.is_some_and(|every| every > 0 && time_step.is_multiple_of(every))
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.time_steps.is_empty() && self.every.is_none_or(|every| every == 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshots_to_geojson() {
+        let snapshot = ContainerSnapshot {
+            time_step: 48,
+            container_idx: 3,
+            container_type: ContainerType::Workplace,
+            x: 10.0,
+            y: 20.5,
+            occupancy: 2,
+            num_susceptible: 1,
+            num_exposed: 0,
+            num_infectious: 1,
+            num_recovered: 0,
+            num_dead: 0,
+        };
+        let geojson = snapshots_to_geojson(&[snapshot]).unwrap();
+        let feature = &geojson["features"][0];
+        assert_eq!(geojson["type"], "FeatureCollection");
+        assert_eq!(feature["geometry"]["coordinates"], json!([10.0, 20.5]));
+        assert_eq!(feature["properties"]["container_type"], "workplace");
+        assert_eq!(feature["properties"]["num_infectious"], 1);
+
+        let times = SnapshotTimes { time_steps: vec![5], every: Some(48) };
+        assert!(times.includes(0) && times.includes(5) && times.includes(96));
+        assert!(!times.includes(6));
+    }
+}
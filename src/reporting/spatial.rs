@@ -0,0 +1,169 @@
+use std::error::Error;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Bounds;
+use crate::agents::Agents;
+use crate::containers::Containers;
+use crate::disease::MixingStrategy;
+use crate::routing::GranularGrid;
+use crate::reporting::{ReportWriter, StateCounts};
+use crate::reporting::stratified::StratifiedCounts;
+
+/// The administrative region of each household, e.g. local authorities, as given in the model
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdminRegions {
+    pub codes: Vec<String>,
+    /// The index into `codes` of each household's region
+    pub household_region: Vec<u32>,
+}
+
+/// The areas the spatial report is broken down into, agents being counted in the area of their
+/// household
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SpatialUnit {
+    /// The cells of a grid over the model's bounds with the given number of rows, see `GranularGrid`
+    Grid { rows: u32 },
+    /// The administrative regions given in the model
+    Regions,
+}
+
+/// The counts of an area on a day, as written to the spatial report
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SpatialMetric {
+    day: u32,
+    unit: String,
+    num_susceptible: usize,
+    num_exposed: usize,
+    num_infectious: usize,
+    num_recovered: usize,
+    num_dead: usize,
+    /// The new infections since the previous row for the area
+    incidence: usize,
+}
+
+/// # Spatial Report
+/// The disease states of the agents in each area of the model, written per day in long format.
+#[derive(Clone)]
+pub struct SpatialReport {
+    unit_names: Vec<String>,
+    stratified: StratifiedCounts,
+}
+
+impl SpatialReport {
+    /// Assigns agents to their area, failing if the grid has no rows, or if reporting by region
+    /// for a model without any or whose regions don't match its households
+    pub fn new<M>(unit: &SpatialUnit, agents: &Agents, containers: &Containers<M>, bounds: &Bounds,
+                  regions: Option<&AdminRegions>) -> Result<Self, Box<dyn Error>>
+        where M: MixingStrategy
+    {
+        let (unit_names, unit_of_agent) = match unit {
+            SpatialUnit::Grid { rows } => {
+                if *rows == 0 {
+                    return Err("the spatial report's grid needs at least one row".into());
+                }
+                let grid = GranularGrid::<()>::new(*rows, bounds);
+                let unit_names = (0..grid.rows)
+                    .flat_map(|row| (0..grid.cols).map(move |col| format!("{}_{}", row, col)))
+                    .collect();
+                let unit_of_agent = agents.household_container.iter().map(|&container_idx| {
+                    let (row, col) = grid.cell_of(containers.get(container_idx).unwrap().pos);
+                    row * grid.cols + col
+                }).collect();
+                (unit_names, unit_of_agent)
+            }
+            SpatialUnit::Regions => {
+                let regions = regions.ok_or("the model doesn't have any regions to report by")?;
+                if regions.household_region.len() != containers.num_households as usize {
+                    return Err(format!("the model has {} households but regions for {}", containers.num_households,
+                                       regions.household_region.len()).into());
+                }
+                if let Some(&region) = regions.household_region.iter().find(|&&region| region as usize >= regions.codes.len()) {
+                    return Err(format!("a household is in region {} but the model only has {} regions", region, regions.codes.len()).into());
+                }
+                // households are the first containers, so their container index is their index in the model
+                let unit_of_agent = agents.household_container.iter()
+                    .map(|&container_idx| regions.household_region[container_idx as usize])
+                    .collect();
+                (regions.codes.clone(), unit_of_agent)
+            }
+        };
+        let stratified = StratifiedCounts::new(unit_names.len(), unit_of_agent, agents);
+        Ok(SpatialReport { unit_names, stratified })
+    }
+
+    #[inline]
+    pub fn unit_names(&self) -> &[String] {
+        &self.unit_names
+    }
+
+    /// The counts of each disease state in each area
+    pub fn counts(&self, agents: &Agents) -> Vec<StateCounts> {
+        self.stratified.counts(agents)
+    }
+
+    /// Writes a row for each area with any agents
+    pub fn write(&mut self, report_writer: &mut ReportWriter<SpatialMetric>, day: u32, agents: &Agents) -> Result<(), Box<dyn Error>> {
+        for (unit, (counts, previous)) in self.stratified.update(agents).iter().enumerate() {
+            if counts.total() == 0 {
+                continue;
+            }
+            report_writer.write(SpatialMetric {
+                day,
+                unit: self.unit_names[unit].clone(),
+                num_susceptible: counts.num_susceptible,
+                num_exposed: counts.num_exposed,
+                num_infectious: counts.num_infectious,
+                num_recovered: counts.num_recovered,
+                num_dead: counts.num_dead,
+                incidence: counts.incidence_since(previous),
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::disease::Uniform;
+    use crate::reporting::{ReportDir, ReportFormat};
+    use crate::Vec2;
+
+    use super::*;
+
+    #[test]
+    fn test_spatial_units() {
+        let positions = [Vec2::new(10.0, 10.0), Vec2::new(90.0, 10.0), Vec2::new(90.0, 90.0)];
+        let containers = Containers::new(&positions, &[], Uniform { transmission_chance: 0.0 });
+        let bounds = Bounds::new(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 100.0));
+        let mut agents = Agents::with_households(vec![30; 4], vec![0, 1, 1, 2]);
+
+        let grid = SpatialReport::new(&SpatialUnit::Grid { rows: 2 }, &agents, &containers, &bounds, None).unwrap();
+        assert_eq!(grid.unit_names(), &["0_0", "0_1", "1_0", "1_1"]);
+        assert_eq!(grid.counts(&agents).iter().map(|counts| counts.total()).collect::<Vec<_>>(), vec![1, 2, 0, 1]);
+        assert!(SpatialReport::new(&SpatialUnit::Grid { rows: 0 }, &agents, &containers, &bounds, None).is_err());
+
+        let regions = AdminRegions { codes: vec!["E1".to_owned(), "E2".to_owned()], household_region: vec![1, 0, 1] };
+        let mut report = SpatialReport::new(&SpatialUnit::Regions, &agents, &containers, &bounds, Some(&regions)).unwrap();
+        agents.disease_statuses[1].infect();
+        agents.disease_statuses[3].infect();
+        let counts = report.counts(&agents);
+        assert_eq!((counts[0].num_susceptible, counts[0].num_exposed), (1, 1));
+        assert_eq!((counts[1].num_susceptible, counts[1].num_exposed), (1, 1));
+        let root = std::env::temp_dir().join(format!("outbreak_sim_spatial_{}", std::process::id()));
+        let report_dir = ReportDir::create(&root, "model", "0", false).unwrap();
+        let mut writer = ReportWriter::create(&report_dir, "spatial", ReportFormat::Csv).unwrap();
+        report.write(&mut writer, 1, &agents).unwrap();
+        writer.finish().unwrap();
+        let rows: Vec<SpatialMetric> = csv::Reader::from_path(writer.path()).unwrap().deserialize().map(Result::unwrap).collect();
+        assert_eq!(rows.iter().map(|row| (row.unit.as_str(), row.incidence)).collect::<Vec<_>>(), vec![("E1", 1), ("E2", 1)]);
+        std::fs::remove_dir_all(root).unwrap();
+
+        let missing = AdminRegions { codes: regions.codes.clone(), household_region: vec![1, 0] };
+        assert!(SpatialReport::new(&SpatialUnit::Regions, &agents, &containers, &bounds, Some(&missing)).is_err());
+        let out_of_range = AdminRegions { codes: regions.codes.clone(), household_region: vec![1, 0, 2] };
+        assert!(SpatialReport::new(&SpatialUnit::Regions, &agents, &containers, &bounds, Some(&out_of_range)).is_err());
+        assert!(SpatialReport::new(&SpatialUnit::Regions, &agents, &containers, &bounds, None).is_err());
+    }
+}
@@ -0,0 +1,65 @@
+use crate::agents::Agents;
+use crate::reporting::StateCounts;
+
+/// The disease states of the agents in each of a number of groups, e.g. age bands or areas, along
+/// with the counts when last updated so the changes between reports can be given.
+///
+/// The incidence of a group is the fall in the number of susceptible agents in it, which is exact
+/// as agents only stop being susceptible by being infected.
+#[derive(Clone)]
+pub(crate) struct StratifiedCounts {
+    group_of_agent: Vec<u32>,
+    previous: Vec<StateCounts>,
+}
+
+impl StratifiedCounts {
+    pub fn new(num_groups: usize, group_of_agent: Vec<u32>, agents: &Agents) -> Self {
+        let mut stratified = StratifiedCounts { group_of_agent, previous: vec![StateCounts::default(); num_groups] };
+        stratified.previous = stratified.counts(agents);
+        stratified
+    }
+
+    /// The group each agent is counted in
+    #[inline]
+    pub fn group_of_agent(&self) -> &[u32] {
+        &self.group_of_agent
+    }
+
+    /// The counts of each disease state in each group
+    pub fn counts(&self, agents: &Agents) -> Vec<StateCounts> {
+        let mut counts = vec![StateCounts::default(); self.previous.len()];
+        for (status, &group) in agents.disease_statuses.iter().zip(self.group_of_agent.iter()) {
+            counts[group as usize].add(status.state);
+        }
+        counts
+    }
+
+    /// Counts each group, returning the counts along with those of the previous update
+    pub fn update(&mut self, agents: &Agents) -> Vec<(StateCounts, StateCounts)> {
+        let counts = self.counts(agents);
+        let previous = std::mem::replace(&mut self.previous, counts.clone());
+        counts.into_iter().zip(previous).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stratified_counts() {
+        let mut agents = Agents::with_households(vec![30; 4], vec![0; 4]);
+        let mut stratified = StratifiedCounts::new(3, vec![0, 1, 1, 0], &agents);
+        agents.disease_statuses[1].infect();
+        agents.disease_statuses[2].infect();
+
+        let updated = stratified.update(&agents);
+        assert_eq!(updated.iter().map(|(counts, previous)| counts.incidence_since(previous)).collect::<Vec<_>>(), vec![0, 2, 0]);
+        assert_eq!(updated[1].0.num_exposed, 2);
+        assert_eq!(updated[2].0.total(), 0);
+        // the next update is relative to this one
+        let updated = stratified.update(&agents);
+        assert!(updated.iter().all(|(counts, previous)| counts.incidence_since(previous) == 0));
+        assert_eq!(updated[1].1.num_exposed, 2);
+    }
+}
@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+
+use crate::agents::Agents;
+use crate::disease::{Hospitalisation, InfectionsBySetting, State};
+use crate::reporting::{AgeBands, StateCounts};
+use crate::shared::get_time_steps_per_day;
+use crate::shared::types::TimeStep;
+
+/// The attack rate of an age band, as written to the concluding report
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct AgeAttackRate {
+    pub age_band: String,
+    pub population: usize,
+    pub infected: usize,
+    pub attack_rate: f32,
+}
+
+/// The summary of the epidemic written to the concluding report
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct EpidemicSummaryMetric {
+    pub population: usize,
+    /// The agents who've ever been infected, including those seeded and imported
+    pub total_infected: usize,
+    pub attack_rate: f32,
+    pub attack_rate_by_age: Vec<AgeAttackRate>,
+    pub infections_by_setting: InfectionsBySetting,
+    /// The share of the population infected in each setting
+    pub attack_rate_by_setting: BTreeMap<&'static str, f32>,
+    /// The most agents exposed or infectious at the start of a day
+    pub peak_prevalence: usize,
+    pub peak_prevalence_day: Option<u32>,
+    /// The days from the first to the last with anyone exposed or infectious
+    pub epidemic_duration_days: Option<u32>,
+    /// Whether nobody was exposed or infectious when the simulation ended
+    pub epidemic_ended: bool,
+    /// `None` without hospitalisation
+    pub total_hospitalisations: Option<usize>,
+    pub total_deaths: usize,
+}
+
+/// # Epidemic Summary
+/// Tracks the course of the epidemic to summarise it at the end of the simulation, which needs
+/// updating every time step to count the new infections in each setting. Prevalence is only counted
+/// at the first time step of each day, as counting it means going through every agent.
+#[derive(Clone)]
+pub struct EpidemicSummary {
+    bands: AgeBands,
+    infections_by_setting: InfectionsBySetting,
+    peak_prevalence: usize,
+    peak_prevalence_day: Option<u32>,
+    first_prevalent_day: Option<u32>,
+    last_prevalent_day: Option<u32>,
+}
+
+impl EpidemicSummary {
+    pub fn new(bands: AgeBands) -> Self {
+        EpidemicSummary {
+            bands,
+            infections_by_setting: InfectionsBySetting::default(),
+            peak_prevalence: 0,
+            peak_prevalence_day: None,
+            first_prevalent_day: None,
+            last_prevalent_day: None,
+        }
+    }
+
+    pub fn update(&mut self, time_step: TimeStep, agents: &Agents) {
+        self.infections_by_setting.add(agents.transmission_log.new_infections());
+        if time_step.is_multiple_of(get_time_steps_per_day()) {
+            self.update_prevalence(time_step / get_time_steps_per_day(), &StateCounts::from_agents(agents));
+        }
+    }
+
+    fn update_prevalence(&mut self, day: u32, counts: &StateCounts) {
+        let prevalence = counts.num_exposed + counts.num_infectious;
+        if prevalence == 0 {
+            return;
+        }
+        if prevalence > self.peak_prevalence {
+            self.peak_prevalence = prevalence;
+            self.peak_prevalence_day = Some(day);
+        }
+        self.first_prevalent_day.get_or_insert(day);
+        self.last_prevalent_day = Some(day);
+    }
+
+    pub fn metric(&self, agents: &Agents, hospitalisation: Option<&Hospitalisation>) -> EpidemicSummaryMetric {
+        let mut population_by_age = vec![0; self.bands.len()];
+        let mut infected_by_age = vec![0; self.bands.len()];
+        for (status, &age) in agents.disease_statuses.iter().zip(agents.ages.iter()) {
+            let band = self.bands.band_of(age);
+            population_by_age[band] += 1;
+            if status.state != State::Susceptible {
+                infected_by_age[band] += 1;
+            }
+        }
+        let counts = StateCounts::from_agents(agents);
+        let population = counts.total();
+        let total_infected = population - counts.num_susceptible;
+
+        let settings = &self.infections_by_setting;
+        let attack_rate_by_setting = [
+            ("household", settings.household),
+            ("workplace", settings.workplace),
+            ("school", settings.school),
+            ("hospital", settings.hospital),
+            ("seeded", settings.seeded),
+            ("imported", settings.imported),
+        ].iter().map(|&(setting, infected)| (setting, attack_rate(infected, population))).collect();
+
+        EpidemicSummaryMetric {
+            population,
+            total_infected,
+            attack_rate: attack_rate(total_infected, population),
+            attack_rate_by_age: (0..self.bands.len()).map(|band| AgeAttackRate {
+                age_band: self.bands.name(band),
+                population: population_by_age[band],
+                infected: infected_by_age[band],
+                attack_rate: attack_rate(infected_by_age[band], population_by_age[band]),
+            }).collect(),
+            infections_by_setting: self.infections_by_setting,
+            attack_rate_by_setting,
+            peak_prevalence: self.peak_prevalence,
+            peak_prevalence_day: self.peak_prevalence_day,
+            epidemic_duration_days: self.first_prevalent_day.zip(self.last_prevalent_day).map(|(first, last)| last - first + 1),
+            epidemic_ended: counts.num_exposed + counts.num_infectious == 0,
+            total_hospitalisations: hospitalisation.map(|hospitalisation| hospitalisation.num_admissions()),
+            total_deaths: counts.num_dead,
+        }
+    }
+}
+
+#[inline]
+fn attack_rate(infected: usize, population: usize) -> f32 {
+    if population == 0 { 0.0 } else { infected as f32 / population as f32 }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::containers::ContainerType;
+    use crate::disease::{InfectionSource, Transmission};
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+
+    use super::*;
+
+    fn infect(agents: &mut Agents, agent_idx: u32, time_step: TimeStep) {
+        agents.disease_statuses[agent_idx as usize].infect();
+        agents.transmission_log.push(Transmission {
+            time_step,
+            infectee: agent_idx,
+            infector: None,
+            source: InfectionSource::Container,
+            container_idx: Some(0),
+            container_type: Some(ContainerType::Household),
+            strain: 0,
+        });
+    }
+
+    #[test]
+    fn test_epidemic_summary() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let tspd = get_time_steps_per_day();
+        let mut agents = Agents::with_households(vec![10, 30, 40, 70], vec![0, 0, 1, 1]);
+        let mut summary = EpidemicSummary::new("18, 65".parse().unwrap());
+
+        agents.transmission_log.push(Transmission::external(0, 0, InfectionSource::Seeded));
+        agents.disease_statuses[0].infect();
+        summary.update(0, &agents);
+        agents.transmission_log.reset_new_infections();
+
+        // infections during a day only count towards the prevalence at the start of the next
+        infect(&mut agents, 1, 1);
+        infect(&mut agents, 2, 1);
+        summary.update(1, &agents);
+        agents.transmission_log.reset_new_infections();
+        assert_eq!((summary.peak_prevalence, summary.peak_prevalence_day), (1, Some(0)));
+
+        summary.update(tspd, &agents);
+        agents.disease_statuses[0].die();
+        agents.disease_statuses[1].progress_infection(13 * tspd);
+        agents.disease_statuses[2].progress_infection(13 * tspd);
+        summary.update(2 * tspd, &agents);
+
+        let metric = summary.metric(&agents, None);
+        assert_eq!(metric.population, 4);
+        assert_eq!(metric.total_infected, 3);
+        assert_eq!(metric.attack_rate, 0.75);
+        assert_eq!(metric.attack_rate_by_age.iter().map(|rate| rate.infected).collect::<Vec<_>>(), vec![1, 2, 0]);
+        assert_eq!(metric.attack_rate_by_age[2].attack_rate, 0.0);
+        assert_eq!((metric.infections_by_setting.seeded, metric.infections_by_setting.household), (1, 2));
+        assert_eq!(metric.attack_rate_by_setting["household"], 0.5);
+        assert_eq!((metric.peak_prevalence, metric.peak_prevalence_day), (3, Some(1)));
+        assert_eq!(metric.epidemic_duration_days, Some(2));
+        assert!(metric.epidemic_ended);
+        assert_eq!(metric.total_hospitalisations, None);
+        assert_eq!(metric.total_deaths, 1);
+    }
+}
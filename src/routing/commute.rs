@@ -0,0 +1,222 @@
+use fast_paths::{FastGraph, PathCalculator};
+use nonmax::NonMaxU64;
+use rand::Rng;
+use rayon::prelude::*;
+
+use crate::agents::Agents;
+use crate::containers::Containers;
+use crate::disease::MixingStrategy;
+use crate::routing::{calculate_direct_commute_time, calculate_public_transit_route, DirectRoutingType, RoutingType, SpatialIndex};
+use crate::shared::{RngStream, seeded_rng};
+use crate::shared::types::TimeStep;
+
+// TODO Change Transit chance to non-zero when transit containers are implemented
+const TRANSIT_CHANCE: f32 = 0.0;
+
+/// One direction of a commute
+#[derive(Debug, Clone)]
+pub struct CommuteLeg {
+    /// The routing type actually used, which falls back to driving if no transit path was found
+    pub routing_type: RoutingType,
+    pub time: TimeStep,
+    /// The transit nodes of the chosen route, empty for direct routes
+    pub transit_route: Vec<usize>,
+}
+
+/// The precomputed commute between an agent's household and occupational container
+#[derive(Debug, Clone)]
+pub struct Commute {
+    /// The routing type the agent has chosen to commute by
+    pub routing_type: RoutingType,
+    pub to_occupation: CommuteLeg,
+    pub to_household: CommuteLeg,
+}
+
+/// # Commute Cache
+/// Households and occupational containers don't move, so rather than routing every `Travel` event
+/// the commute of each agent is calculated once in both directions and looked up afterwards.
+///
+/// Entries only need recalculating when an agent changes their routing type, or when the transit
+/// graph changes (at which point all transit commutes are recalculated).
+#[derive(Clone, Default)]
+pub struct CommuteCache {
+    commutes: Vec<Option<Commute>>,
+}
+
+impl CommuteCache {
+    /// Calculates the commutes of all agents with an occupational container in parallel
+    pub fn new<M, S>(agents: &Agents, containers: &Containers<M>, transit_stops: &S, fast_graph: &FastGraph) -> Self
+        where M: MixingStrategy, S: SpatialIndex<usize> + Sync
+    {
+        let commutes = agents.household_container.par_iter()
+            .zip(agents.occupational_container.par_iter())
+            .enumerate()
+            .map_init(
+                || fast_paths::create_calculator(fast_graph),
+                |path_calculator, (agent_idx, (&household_idx, &occupational_idx))| {
+                    occupational_idx.map(|occupational_idx| {
+                        let routing_type = choose_routing_type(&mut seeded_rng(RngStream::Commutes, &[agent_idx as u64]));
+                        calculate_commute(routing_type, NonMaxU64::new(household_idx).unwrap(), occupational_idx,
+                                          containers, transit_stops, fast_graph, path_calculator)
+                    })
+                })
+            .collect();
+
+        Self { commutes }
+    }
+
+    #[inline]
+    pub fn get(&self, agent_idx: u32) -> Option<&Commute> {
+        self.commutes.get(agent_idx as usize).and_then(|commute| commute.as_ref())
+    }
+
+    /// Returns the cached leg for an agent travelling between the two given containers, if the
+    /// journey is between their household and occupational container
+    #[inline]
+    pub fn get_leg(&self, agents: &Agents, agent_idx: u32, from_container_idx: NonMaxU64, to_container_idx: NonMaxU64) -> Option<&CommuteLeg> {
+        let commute = self.get(agent_idx)?;
+        let household_idx = agents.household_container[agent_idx as usize];
+        let occupational_idx = agents.occupational_container[agent_idx as usize]?;
+
+        if from_container_idx.get() == household_idx && to_container_idx == occupational_idx {
+            Some(&commute.to_occupation)
+        } else if from_container_idx == occupational_idx && to_container_idx.get() == household_idx {
+            Some(&commute.to_household)
+        } else {
+            None
+        }
+    }
+
+    /// Changes the routing type an agent commutes by, recalculating their commute if it changed
+    pub fn set_routing_type<M, S>(&mut self, agent_idx: u32, routing_type: RoutingType, agents: &Agents, containers: &Containers<M>,
+                               transit_stops: &S, fast_graph: &FastGraph)
+        where M: MixingStrategy, S: SpatialIndex<usize> + Sync
+    {
+        let occupational_idx = match agents.occupational_container[agent_idx as usize] {
+            Some(occupational_idx) => { occupational_idx }
+            None => { return; }
+        };
+        if let Some(commute) = self.get(agent_idx) {
+            if commute.routing_type == routing_type {
+                return;
+            }
+        }
+
+        let household_idx = NonMaxU64::new(agents.household_container[agent_idx as usize]).unwrap();
+        let mut path_calculator = fast_paths::create_calculator(fast_graph);
+        self.commutes[agent_idx as usize] = Some(calculate_commute(routing_type, household_idx, occupational_idx,
+                                                                   containers, transit_stops, fast_graph, &mut path_calculator));
+    }
+
+    /// Recalculates every commute by transit, to be called whenever the transit graph changes
+    pub fn refresh_transit<M, S>(&mut self, agents: &Agents, containers: &Containers<M>, transit_stops: &S, fast_graph: &FastGraph)
+        where M: MixingStrategy, S: SpatialIndex<usize> + Sync
+    {
+        self.commutes.par_iter_mut()
+            .zip(agents.household_container.par_iter().zip(agents.occupational_container.par_iter()))
+            .filter(|(commute, _)| matches!(commute, Some(Commute { routing_type: RoutingType::Transit, .. })))
+            .for_each_init(
+                || fast_paths::create_calculator(fast_graph),
+                |path_calculator, (commute, (&household_idx, &occupational_idx))| {
+                    *commute = Some(calculate_commute(RoutingType::Transit, NonMaxU64::new(household_idx).unwrap(), occupational_idx.unwrap(),
+                                                      containers, transit_stops, fast_graph, path_calculator));
+                });
+    }
+}
+
+#[inline]
+fn choose_routing_type<R>(rng: &mut R) -> RoutingType
+    where R: Rng + ?Sized
+{
+    if rng.gen::<f32>() < TRANSIT_CHANCE { RoutingType::Transit } else { RoutingType::Direct(DirectRoutingType::Driving) }
+}
+
+fn calculate_commute<M, S>(routing_type: RoutingType, household_idx: NonMaxU64, occupational_idx: NonMaxU64, containers: &Containers<M>,
+                        transit_stops: &S, fast_graph: &FastGraph, path_calculator: &mut PathCalculator) -> Commute
+    where M: MixingStrategy, S: SpatialIndex<usize>
+{
+    Commute {
+        routing_type,
+        to_occupation: calculate_leg(routing_type, household_idx, occupational_idx, containers, transit_stops, fast_graph, path_calculator),
+        to_household: calculate_leg(routing_type, occupational_idx, household_idx, containers, transit_stops, fast_graph, path_calculator),
+    }
+}
+
+fn calculate_leg<M, S>(routing_type: RoutingType, from_container_idx: NonMaxU64, to_container_idx: NonMaxU64, containers: &Containers<M>,
+                    transit_stops: &S, fast_graph: &FastGraph, path_calculator: &mut PathCalculator) -> CommuteLeg
+    where M: MixingStrategy, S: SpatialIndex<usize>
+{
+    let (routing_type, time, transit_route) = match routing_type {
+        RoutingType::Transit => {
+            match calculate_public_transit_route(containers, transit_stops, path_calculator, fast_graph, from_container_idx, to_container_idx) {
+                Ok((time, route)) => { (RoutingType::Transit, time, route) }
+                Err(_) => {
+                    (RoutingType::Direct(DirectRoutingType::Driving),
+                     calculate_direct_commute_time(containers, DirectRoutingType::Driving, from_container_idx, to_container_idx),
+                     Vec::new())
+                }
+            }
+        }
+        RoutingType::Direct(direct_routing_type) => {
+            (routing_type, calculate_direct_commute_time(containers, direct_routing_type, from_container_idx, to_container_idx), Vec::new())
+        }
+    };
+
+    CommuteLeg {
+        routing_type,
+        time: if time == 0 { 1 } else { time },
+        transit_route,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fast_paths::InputGraph;
+
+    use crate::disease::Uniform;
+    use crate::routing::KdTree;
+    use crate::shared::{GLOBAL_PARAMS, GlobalSimParams};
+    use crate::Vec2;
+
+    use super::*;
+
+    #[test]
+    fn test_commute_cache() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let household_positions = [Vec2::new(0.0, 0.0), Vec2::new(1000.0, 0.0)];
+        let workplace_positions = [Vec2::new(0.0, 5000.0)];
+        let containers = Containers::new(&household_positions, &workplace_positions, Uniform { transmission_chance: 0.0 });
+        let mut agents = Agents::with_households(vec![30, 30], vec![0, 1]);
+        agents.occupational_container[0] = NonMaxU64::new(containers.get_workplace_idx(0));
+        // a stop at the household and another at the workplace, only connected towards the workplace
+        let transit_stops = KdTree::new(vec![(household_positions[0], 0), (workplace_positions[0], 1)]);
+        let mut input_graph = InputGraph::new();
+        input_graph.add_edge(0, 1, 3);
+        input_graph.freeze();
+        let fast_graph = fast_paths::prepare(&input_graph);
+
+        let mut commute_cache = CommuteCache::new(&agents, &containers, &transit_stops, &fast_graph);
+        let household = NonMaxU64::new(0).unwrap();
+        let workplace = NonMaxU64::new(containers.get_workplace_idx(0)).unwrap();
+        let driving = RoutingType::Direct(DirectRoutingType::Driving);
+        assert_eq!(commute_cache.get(0).unwrap().routing_type, driving);
+        let to_occupation = commute_cache.get_leg(&agents, 0, household, workplace).unwrap();
+        assert_eq!((to_occupation.routing_type, to_occupation.time), (driving, 1));
+        assert!(commute_cache.get_leg(&agents, 0, workplace, household).is_some());
+        assert!(commute_cache.get_leg(&agents, 0, household, NonMaxU64::new(1).unwrap()).is_none());
+
+        // those without a workplace don't commute, whatever they're set to commute by
+        commute_cache.set_routing_type(1, RoutingType::Transit, &agents, &containers, &transit_stops, &fast_graph);
+        assert!(commute_cache.get(1).is_none());
+        assert!(commute_cache.get_leg(&agents, 1, NonMaxU64::new(1).unwrap(), workplace).is_none());
+
+        // the way home has no transit path, so falls back to driving
+        commute_cache.set_routing_type(0, RoutingType::Transit, &agents, &containers, &transit_stops, &fast_graph);
+        let commute = commute_cache.get(0).unwrap();
+        assert_eq!(commute.routing_type, RoutingType::Transit);
+        assert_eq!((commute.to_occupation.routing_type, commute.to_occupation.time), (RoutingType::Transit, 3));
+        assert_eq!(commute.to_occupation.transit_route, vec![0, 1]);
+        assert_eq!(commute.to_household.routing_type, driving);
+        assert!(commute.to_household.transit_route.is_empty());
+    }
+}
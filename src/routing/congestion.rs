@@ -0,0 +1,134 @@
+use std::collections::VecDeque;
+
+use crate::shared::types::TimeStep;
+
+/// # Congestion
+/// Tracks how many agents are on the road in each time step and scales driving times by a BPR
+/// (Bureau of Public Roads) volume-delay function, `t = t0 * (1 + alpha * (volume / capacity)^beta)`.
+///
+/// The road network isn't modelled, so volume and capacity are for the whole region rather than
+/// individual links, which is enough for rush-hour peaks to lengthen commutes.
+#[derive(Clone, Debug)]
+pub struct Congestion {
+    /// Agents per time step the roads can carry before travel times rise sharply, `None` disables
+    /// the delay while volumes are still tracked
+    pub capacity: Option<f32>,
+    pub alpha: f32,
+    pub beta: f32,
+    /// Number of agents on the road in each upcoming time step, the front being the current one
+    on_road: VecDeque<u32>,
+    volume: u32,
+}
+
+impl Congestion {
+    pub fn new(capacity: Option<f32>, alpha: f32, beta: f32) -> Self {
+        Congestion {
+            capacity,
+            alpha,
+            beta,
+            on_road: VecDeque::new(),
+            volume: 0,
+        }
+    }
+
+    /// The number of agents on the road in the current time step
+    #[inline]
+    pub fn volume(&self) -> u32 {
+        self.volume
+    }
+
+    /// Sets the volume of the current time step from the agents still on the road and those
+    /// departing within it
+    #[inline]
+    pub fn begin_time_step(&mut self, num_departing: u32) {
+        self.volume = self.on_road.front().copied().unwrap_or(0) + num_departing;
+    }
+
+    /// Moves on to the next time step
+    #[inline]
+    pub fn end_time_step(&mut self) {
+        self.on_road.pop_front();
+    }
+
+    /// Scales a free-flow driving time by the current volume, and records the agent being on the
+    /// road for the resulting duration
+    #[inline]
+    pub fn driving_time(&mut self, free_flow_time: TimeStep) -> TimeStep {
+        let time = match self.capacity {
+            Some(capacity) => {
+                let factor = bpr_factor(self.volume as f32, capacity, self.alpha, self.beta);
+                ((free_flow_time as f32 * factor).round() as TimeStep).max(1)
+            }
+            None => { free_flow_time }
+        };
+
+        if self.on_road.len() < time as usize {
+            self.on_road.resize(time as usize, 0);
+        }
+        self.on_road.iter_mut().take(time as usize).for_each(|num_on_road| *num_on_road += 1);
+        time
+    }
+
+    /// Takes a driving agent whose journey has been cut short, e.g. by being admitted to hospital,
+    /// off the road for the rest of it, where `remaining_time_steps` counts from the current time
+    /// step up to their arrival
+    #[inline]
+    pub fn cancel_driving(&mut self, remaining_time_steps: TimeStep) {
+        self.on_road.iter_mut().take(remaining_time_steps as usize)
+            .for_each(|num_on_road| *num_on_road = num_on_road.saturating_sub(1));
+    }
+}
+
+/// The BPR volume-delay multiplier applied to free-flow travel times
+#[inline]
+pub fn bpr_factor(volume: f32, capacity: f32, alpha: f32, beta: f32) -> f32 {
+    1.0 + alpha * (volume / capacity).powf(beta)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bpr_factor() {
+        assert!((bpr_factor(0.0, 100.0, 0.15, 4.0) - 1.0).abs() < f32::EPSILON);
+        assert!((bpr_factor(100.0, 100.0, 0.15, 4.0) - 1.15).abs() < f32::EPSILON);
+        assert!((bpr_factor(200.0, 100.0, 0.15, 4.0) - 3.4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_driving_time_tracks_volume() {
+        let mut congestion = Congestion::new(Some(1.0), 1.0, 1.0);
+
+        congestion.begin_time_step(1);
+        assert_eq!(congestion.driving_time(3), 6);
+        congestion.end_time_step();
+
+        congestion.begin_time_step(0);
+        assert_eq!(congestion.volume(), 1);
+        congestion.end_time_step();
+    }
+
+    #[test]
+    fn test_cancel_driving() {
+        let mut congestion = Congestion::new(None, 1.0, 1.0);
+        congestion.begin_time_step(2);
+        assert_eq!(congestion.driving_time(4), 4);
+        assert_eq!(congestion.driving_time(2), 2);
+        congestion.end_time_step();
+
+        // the first agent is taken off the road a time step into their journey, which would have
+        // them arrive at the fourth
+        congestion.cancel_driving(3);
+        let volumes: Vec<u32> = (0..3).map(|_| {
+            congestion.begin_time_step(0);
+            congestion.end_time_step();
+            congestion.volume()
+        }).collect();
+        assert_eq!(volumes, vec![1, 0, 0]);
+        // nothing is left to take off the road
+        congestion.cancel_driving(2);
+        congestion.begin_time_step(0);
+        assert_eq!(congestion.volume(), 0);
+    }
+}
@@ -1,8 +1,10 @@
-use std::cmp::min;
+use std::cmp::{max, min};
 use std::iter;
 use std::ops::{Index, IndexMut};
 
-use crate::Bounds;
+use crate::{Bounds, Vec2};
+use crate::routing::{distance_f32, get_coords_on_perimeter};
+use crate::routing::spatial_index::{Neighbour, sort_by_dist, SpatialIndex};
 
 #[derive(PartialEq, Debug, Clone)]
 pub struct GranularGrid<T> {
@@ -27,12 +29,12 @@ impl<T> GranularGrid<T> {
     /// # Arguments
     ///
     /// * `rows` - Number of rows to use to partition the space, this defines the ratio (granularity)
-    /// of the grid by rows / bounds.max().x()
+    ///   of the grid by rows / bounds.max().x()
     ///
     /// * `bounds` - Bounding box of the underlying space (non-inclusive). Number of columns is
-    /// decided by the calculated ratio and the maximum y value
+    ///   decided by the calculated ratio and the maximum y value
     pub fn new(rows: u32, bounds: &Bounds) -> Self {
-        let idx_to_coord_ratio = rows as f32 / bounds.max().y() as f32;
+        let idx_to_coord_ratio = rows as f32 / bounds.max().y();
         let cols = (bounds.max().x() * idx_to_coord_ratio).ceil() as u32;
         let size = (rows as usize).checked_mul(cols as usize).expect("too big");
 
@@ -46,11 +48,90 @@ impl<T> GranularGrid<T> {
         }
     }
 
-    /// Returns the vector of elements at a given cell's (row,col) index
+    /// Returns the vector of elements at a given cell's (row,col) index, indices past the edge of
+    /// the grid are clamped to the last row or column
     pub fn get_int_index(&self, row: u32, col: u32) -> &Vec<T> {
-        // TODO floating-point error can lead to bad accesses
-        let index = min(row as usize * self.cols as usize + col as usize, self.cells.len() - 1);
-        &self.cells[index]
+        let row = min(row, self.rows - 1) as usize;
+        let col = min(col, self.cols - 1) as usize;
+        &self.cells[row * self.cols as usize + col]
+    }
+
+    /// Returns the (row, col) index of the cell containing a position, positions outside of the
+    /// grid are clamped to the nearest cell
+    #[inline]
+    pub fn cell_of(&self, pos: Vec2) -> (u32, u32) {
+        let row = (pos.y() * self.idx_to_coord_ratio).max(0.0) as u32;
+        let col = (pos.x() * self.idx_to_coord_ratio).max(0.0) as u32;
+        (min(row, self.rows - 1), min(col, self.cols - 1))
+    }
+}
+
+impl<T> GranularGrid<(Vec2, T)> {
+    /// Returns a new GranularGrid of positioned items, which can be used as a SpatialIndex
+    pub fn from_points<I>(points: I, rows: u32, bounds: &Bounds) -> Self
+        where I: IntoIterator<Item=(Vec2, T)>
+    {
+        let mut grid = Self::new(rows, bounds);
+        for (pos, item) in points {
+            let (row, col) = grid.cell_of(pos);
+            let index = row as usize * grid.cols as usize + col as usize;
+            grid.cells[index].push((pos, item));
+        }
+        grid
+    }
+
+    #[inline]
+    fn neighbours_in_ring(&self, centre: (u32, u32), ring: u32, pos: Vec2, max_dist: f32, found: &mut Vec<Neighbour<T>>)
+        where T: Copy
+    {
+        for (row, col) in get_coords_on_perimeter(centre.0 as isize, centre.1 as isize, ring as isize, self.rows, self.cols) {
+            for &(point, item) in self.get_int_index(row, col) {
+                let dist = distance_f32(pos, point);
+                if dist <= max_dist {
+                    found.push(Neighbour { item, dist });
+                }
+            }
+        }
+    }
+}
+
+/// Searches squares of increasing size around the cell containing the position. Every item in the
+/// square of cells `ring` away from the centre is at least `(ring - 1) * cell size` away, so the
+/// search stops as soon as that can't beat what's been found.
+impl<T: Copy> SpatialIndex<T> for GranularGrid<(Vec2, T)> {
+    fn nearest(&self, pos: Vec2, k: usize, max_dist: f32) -> Vec<Neighbour<T>> {
+        let mut found = Vec::new();
+        if k == 0 {
+            return found;
+        }
+        let centre = self.cell_of(pos);
+        let cell_size = 1.0 / self.idx_to_coord_ratio;
+
+        for ring in 0..=max(self.rows, self.cols) {
+            let lower_bound = ring.saturating_sub(1) as f32 * cell_size;
+            if lower_bound > max_dist || (found.len() >= k && found[k - 1].dist <= lower_bound) {
+                break;
+            }
+            self.neighbours_in_ring(centre, ring, pos, max_dist, &mut found);
+            sort_by_dist(&mut found);
+            found.truncate(k);
+        }
+        found
+    }
+
+    fn within_radius(&self, pos: Vec2, radius: f32) -> Vec<Neighbour<T>> {
+        let mut found = Vec::new();
+        let centre = self.cell_of(pos);
+        let cell_size = 1.0 / self.idx_to_coord_ratio;
+
+        for ring in 0..=max(self.rows, self.cols) {
+            if ring.saturating_sub(1) as f32 * cell_size > radius {
+                break;
+            }
+            self.neighbours_in_ring(centre, ring, pos, radius, &mut found);
+        }
+        sort_by_dist(&mut found);
+        found
     }
 }
 
@@ -0,0 +1,143 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::routing::distance_f32;
+use crate::routing::spatial_index::{Neighbour, sort_by_dist, SpatialIndex};
+use crate::Vec2;
+
+/// # KD-Tree
+/// A static 2-dimensional tree of positioned items, stored implicitly in a single vec where the
+/// median of every sub-slice is the node splitting it, alternating between splitting on x and y.
+#[derive(Clone)]
+pub struct KdTree<T> {
+    points: Vec<(Vec2, T)>,
+}
+
+/// A candidate in a k-nearest search, ordered by distance so the furthest is at the top of the heap
+#[derive(PartialEq)]
+struct Candidate {
+    dist: f32,
+    idx: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist.partial_cmp(&other.dist).unwrap_or(Ordering::Equal)
+    }
+}
+
+struct NearestQuery {
+    pos: Vec2,
+    k: usize,
+    max_dist: f32,
+}
+
+#[inline]
+fn coord(pos: Vec2, depth: usize) -> f32 {
+    if depth.is_multiple_of(2) { pos.x() } else { pos.y() }
+}
+
+impl<T: Copy> KdTree<T> {
+    pub fn new(mut points: Vec<(Vec2, T)>) -> Self {
+        build(&mut points, 0);
+        Self { points }
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool { self.points.is_empty() }
+
+    fn search_nearest(&self, start: usize, end: usize, depth: usize, query: &NearestQuery, heap: &mut BinaryHeap<Candidate>) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let point = self.points[mid].0;
+
+        let dist = distance_f32(query.pos, point);
+        if dist <= query.max_dist {
+            if heap.len() < query.k {
+                heap.push(Candidate { dist, idx: mid });
+            } else if dist < heap.peek().unwrap().dist {
+                heap.pop();
+                heap.push(Candidate { dist, idx: mid });
+            }
+        }
+
+        let diff = coord(query.pos, depth) - coord(point, depth);
+        let (near, far) = if diff < 0.0 { ((start, mid), (mid + 1, end)) } else { ((mid + 1, end), (start, mid)) };
+        self.search_nearest(near.0, near.1, depth + 1, query, heap);
+
+        let furthest = if heap.len() < query.k { query.max_dist } else { heap.peek().unwrap().dist.min(query.max_dist) };
+        if diff.abs() <= furthest {
+            self.search_nearest(far.0, far.1, depth + 1, query, heap);
+        }
+    }
+
+    fn search_radius(&self, start: usize, end: usize, depth: usize, pos: Vec2, radius: f32, found: &mut Vec<Neighbour<T>>) {
+        if start >= end {
+            return;
+        }
+        let mid = start + (end - start) / 2;
+        let (point, item) = self.points[mid];
+
+        let dist = distance_f32(pos, point);
+        if dist <= radius {
+            found.push(Neighbour { item, dist });
+        }
+
+        let diff = coord(pos, depth) - coord(point, depth);
+        if diff < 0.0 || diff.abs() <= radius {
+            self.search_radius(start, mid, depth + 1, pos, radius, found);
+        }
+        if diff >= 0.0 || diff.abs() <= radius {
+            self.search_radius(mid + 1, end, depth + 1, pos, radius, found);
+        }
+    }
+}
+
+fn build<T>(points: &mut [(Vec2, T)], depth: usize) {
+    if points.len() <= 1 {
+        return;
+    }
+    let mid = points.len() / 2;
+    points.select_nth_unstable_by(mid, |a, b| {
+        coord(a.0, depth).partial_cmp(&coord(b.0, depth)).unwrap_or(Ordering::Equal)
+    });
+    let (left, right) = points.split_at_mut(mid);
+    build(left, depth + 1);
+    build(&mut right[1..], depth + 1);
+}
+
+impl<T: Copy> SpatialIndex<T> for KdTree<T> {
+    fn nearest(&self, pos: Vec2, k: usize, max_dist: f32) -> Vec<Neighbour<T>> {
+        if k == 0 {
+            return Vec::new();
+        }
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        self.search_nearest(0, self.points.len(), 0, &NearestQuery { pos, k, max_dist }, &mut heap);
+
+        heap.into_sorted_vec().into_iter()
+            .map(|candidate| Neighbour { item: self.points[candidate.idx].1, dist: candidate.dist })
+            .collect()
+    }
+
+    fn within_radius(&self, pos: Vec2, radius: f32) -> Vec<Neighbour<T>> {
+        let mut found = Vec::new();
+        self.search_radius(0, self.points.len(), 0, pos, radius, &mut found);
+        sort_by_dist(&mut found);
+        found
+    }
+}
@@ -2,38 +2,89 @@ use std::cmp::{max, min};
 
 use fast_paths::{FastGraph, PathCalculator};
 use nonmax::NonMaxU64;
-use rand::{Rng, thread_rng};
+use rand::Rng;
 use rand::seq::{IteratorRandom, SliceRandom};
 
 use crate::{Bounds, Vec2};
 use crate::containers::Containers;
 use crate::disease::MixingStrategy;
 use crate::flatbuffer::TransitGraph;
+use crate::routing::commute::CommuteCache;
+use crate::routing::congestion::Congestion;
 pub use crate::routing::granular_grid::GranularGrid;
+pub use crate::routing::kd_tree::KdTree;
+pub use crate::routing::spatial_index::{Neighbour, SpatialIndex};
 use crate::shared::{get_cycling_speed, get_driving_speed, get_walking_speed};
 use crate::shared::types::TimeStep;
 
 pub mod transit;
+pub mod commute;
+pub mod congestion;
 mod granular_grid;
+mod kd_tree;
+mod spatial_index;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum RoutingType {
     Transit,
     Direct(DirectRoutingType),
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum DirectRoutingType {
     Walking,
     Cycling,
     Driving,
 }
 
+/// The routing state needed to move agents between containers during a time step
+pub struct Router<'a> {
+    pub commute_cache: &'a CommuteCache,
+    pub congestion: &'a mut Congestion,
+    pub transit_stops: &'a KdTree<usize>,
+    pub fast_graph: &'a FastGraph,
+    pub transit_path_calculator: PathCalculator,
+}
+
+impl<'a> Router<'a> {
+    pub fn new(commute_cache: &'a CommuteCache, congestion: &'a mut Congestion, transit_stops: &'a KdTree<usize>,
+               fast_graph: &'a FastGraph) -> Self {
+        Router {
+            commute_cache,
+            congestion,
+            transit_stops,
+            fast_graph,
+            transit_path_calculator: fast_paths::create_calculator(fast_graph),
+        }
+    }
+}
+
 #[inline]
 pub fn distance_f32(p1: Vec2, p2: Vec2) -> f32 {
     ((p2.x() - p1.x()).powi(2) + (p2.y() - p1.y()).powi(2)).sqrt()
 }
 
+/// Returns the point a given fraction of the way along a path of straight line segments, where the
+/// fraction is of the path's total length
+pub fn interpolate_along_path(path: &[Vec2], fraction: f32) -> Vec2 {
+    let total_length: f32 = path.windows(2).map(|segment| distance_f32(segment[0], segment[1])).sum();
+    if total_length <= 0.0 {
+        return path.first().copied().unwrap_or_else(|| Vec2::new(0.0, 0.0));
+    }
+
+    let mut remaining = fraction.clamp(0.0, 1.0) * total_length;
+    for segment in path.windows(2) {
+        let length = distance_f32(segment[0], segment[1]);
+        if remaining <= length && length > 0.0 {
+            let t = remaining / length;
+            return Vec2::new(segment[0].x() + (segment[1].x() - segment[0].x()) * t,
+                             segment[0].y() + (segment[1].y() - segment[0].y()) * t);
+        }
+        remaining -= length;
+    }
+    *path.last().unwrap()
+}
+
 #[inline]
 pub fn calculate_direct_commute_time<M>(containers: &Containers<M>, routing_type: DirectRoutingType,
                                         from_container_idx: NonMaxU64, to_container_idx: NonMaxU64) -> TimeStep
@@ -57,24 +108,34 @@ pub fn calculate_direct_commute_time<M>(containers: &Containers<M>, routing_type
 }
 
 #[inline]
-pub fn calculate_public_transit_commute_time<'e, M>(containers: &Containers<M>, transit_grid: &GranularGrid<usize>,
-                                                    transit_path_calculator: &mut PathCalculator, fast_graph: &FastGraph,
-                                                    from_container_idx: NonMaxU64, to_container_idx: NonMaxU64) -> Result<TimeStep, &'e str>
-    where M: MixingStrategy
+pub fn calculate_public_transit_commute_time<'e, M, S>(containers: &Containers<M>, transit_stops: &S,
+                                                       transit_path_calculator: &mut PathCalculator, fast_graph: &FastGraph,
+                                                       from_container_idx: NonMaxU64, to_container_idx: NonMaxU64) -> Result<TimeStep, &'e str>
+    where M: MixingStrategy, S: SpatialIndex<usize>
+{
+    calculate_public_transit_route(containers, transit_stops, transit_path_calculator, fast_graph, from_container_idx, to_container_idx)
+        .map(|(commute_time, _)| commute_time)
+}
+
+/// Finds a transit route between two containers, returning its duration along with the transit
+/// nodes that make up the route. The nearest few stops to either end are tried in order of
+/// distance until a path between them is found.
+#[inline]
+pub fn calculate_public_transit_route<'e, M, S>(containers: &Containers<M>, transit_stops: &S,
+                                                transit_path_calculator: &mut PathCalculator, fast_graph: &FastGraph,
+                                                from_container_idx: NonMaxU64, to_container_idx: NonMaxU64) -> Result<(TimeStep, Vec<usize>), &'e str>
+    where M: MixingStrategy, S: SpatialIndex<usize>
 {
     let start_pos = containers.get(from_container_idx.get()).unwrap().pos;
     let end_pos = containers.get(to_container_idx.get()).unwrap().pos;
-    let mut rng = thread_rng();
 
-    let possible_start_nodes = sample_nearby_from_grid(transit_grid, (start_pos.y(), start_pos.x()), 3_500.0, 5, &mut rng);
-    let possible_end_nodes = sample_nearby_from_grid(transit_grid, (end_pos.y(), end_pos.x()), 3_500.0, 5, &mut rng);
+    let start_nodes = transit_stops.nearest(start_pos, 5, 3_500.0);
+    let end_nodes = transit_stops.nearest(end_pos, 5, 3_500.0);
 
-    if let (Some(start_nodes), Some(end_nodes)) = (possible_start_nodes, possible_end_nodes) {
-        for end_node in end_nodes {
-            for start_node in &start_nodes {
-                if let Some(shortest_path) = transit_path_calculator.calc_path(fast_graph, *start_node, end_node) {
-                    return Ok(shortest_path.get_weight() as TimeStep);
-                }
+    for end_node in &end_nodes {
+        for start_node in &start_nodes {
+            if let Some(shortest_path) = transit_path_calculator.calc_path(fast_graph, start_node.item, end_node.item) {
+                return Ok((shortest_path.get_weight() as TimeStep, shortest_path.get_nodes().clone()));
             }
         }
     }
@@ -149,7 +210,7 @@ pub fn sample_nearby_from_grid<R>(grid: &GranularGrid<usize>, centre: (f32, f32)
         let sampled = get_coords_on_perimeter(pos.0 as isize, pos.1 as isize, dist as isize, grid.rows, grid.cols)
             .into_iter()
             .flat_map(|pos| {
-                grid.get_int_index(pos.0, pos.1).clone()
+                grid.get_int_index(pos.0, pos.1).iter().copied()
             })
             .choose_multiple(rng, num_samples);
         if !sampled.is_empty() {
@@ -164,6 +225,20 @@ pub fn sample_nearby_from_grid<R>(grid: &GranularGrid<usize>, centre: (f32, f32)
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_interpolate_along_path() {
+        let path = vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(10.0, 10.0)];
+
+        let halfway = interpolate_along_path(&path, 0.5);
+        assert!((halfway.x() - 10.0).abs() < f32::EPSILON && halfway.y().abs() < f32::EPSILON);
+
+        let three_quarters = interpolate_along_path(&path, 0.75);
+        assert!((three_quarters.x() - 10.0).abs() < f32::EPSILON && (three_quarters.y() - 5.0).abs() < f32::EPSILON);
+
+        let end = interpolate_along_path(&path, 1.5);
+        assert!((end.x() - 10.0).abs() < f32::EPSILON && (end.y() - 10.0).abs() < f32::EPSILON);
+    }
+
     #[test]
     fn test_get_coords_on_perimeter() {
         let coords = get_coords_on_perimeter(2, 0, 0, 7, 7);
@@ -0,0 +1,84 @@
+use crate::Vec2;
+
+/// An item found by a spatial query, along with its distance from the queried position
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Neighbour<T> {
+    pub item: T,
+    pub dist: f32,
+}
+
+/// # Spatial Index
+/// A structure of positioned items that can be queried for the items nearest to a position.
+///
+/// Results are always sorted by ascending distance.
+pub trait SpatialIndex<T> {
+    /// Returns up to `k` of the items closest to `pos`, ignoring anything further than `max_dist`
+    fn nearest(&self, pos: Vec2, k: usize, max_dist: f32) -> Vec<Neighbour<T>>;
+
+    /// Returns every item within `radius` of `pos`
+    fn within_radius(&self, pos: Vec2, radius: f32) -> Vec<Neighbour<T>>;
+}
+
+#[inline]
+pub(crate) fn sort_by_dist<T>(neighbours: &mut [Neighbour<T>]) {
+    neighbours.sort_by(|a, b| a.dist.partial_cmp(&b.dist).unwrap_or(std::cmp::Ordering::Equal));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Bounds;
+    use crate::routing::{distance_f32, GranularGrid, KdTree};
+
+    use super::*;
+
+    fn points() -> Vec<(Vec2, usize)> {
+        (0..400).map(|idx| {
+            // Deterministic but scattered points across a 100x80 space
+            let x = ((idx * 37) % 100) as f32 + 0.5;
+            let y = ((idx * 53) % 80) as f32 + 0.25;
+            (Vec2::new(x, y), idx)
+        }).collect()
+    }
+
+    fn brute_force(points: &[(Vec2, usize)], pos: Vec2, max_dist: f32) -> Vec<Neighbour<usize>> {
+        let mut neighbours: Vec<Neighbour<usize>> = points.iter()
+            .map(|&(point, item)| Neighbour { item, dist: distance_f32(pos, point) })
+            .filter(|neighbour| neighbour.dist <= max_dist)
+            .collect();
+        sort_by_dist(&mut neighbours);
+        neighbours
+    }
+
+    fn assert_same_dists(actual: &[Neighbour<usize>], expected: &[Neighbour<usize>]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!((actual.dist - expected.dist).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn test_nearest_matches_brute_force() {
+        let points = points();
+        let bounds = Bounds::new(&Vec2::new(0.0, 0.0), &Vec2::new(100.0, 80.0));
+        let grid = GranularGrid::from_points(points.iter().copied(), 8, &bounds);
+        let tree = KdTree::new(points.clone());
+
+        for &pos in [Vec2::new(0.0, 0.0), Vec2::new(50.3, 40.1), Vec2::new(99.0, 79.0), Vec2::new(12.0, 70.0)].iter() {
+            let expected = brute_force(&points, pos, f32::INFINITY);
+            assert_same_dists(&tree.nearest(pos, 7, f32::INFINITY), &expected[..7]);
+            assert_same_dists(&grid.nearest(pos, 7, f32::INFINITY), &expected[..7]);
+
+            let expected = brute_force(&points, pos, 15.0);
+            assert_same_dists(&tree.within_radius(pos, 15.0), &expected);
+            assert_same_dists(&grid.within_radius(pos, 15.0), &expected);
+        }
+    }
+
+    #[test]
+    fn test_nearest_respects_max_dist() {
+        let tree = KdTree::new(vec![(Vec2::new(0.0, 0.0), 0), (Vec2::new(10.0, 0.0), 1)]);
+        let nearest = tree.nearest(Vec2::new(1.0, 0.0), 2, 5.0);
+        assert_eq!(nearest.len(), 1);
+        assert_eq!(nearest[0].item, 0);
+    }
+}
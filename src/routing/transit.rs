@@ -1,12 +1,141 @@
+use std::{fmt, fs};
 use std::error::Error;
 use std::fs::File;
-use std::path::Path;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
-use fast_paths::{FastGraph, InputGraph};
+use fast_paths::{FastGraph, InputGraph, Params};
 
 use crate::TransitGraph;
 
+/// Identifies a file as a cached fast graph
+const FAST_GRAPH_CACHE_MAGIC: &[u8; 4] = b"OSFG";
+/// Bumped whenever the cache file layout, or the way the graph is preprocessed, changes
+const FAST_GRAPH_CACHE_VERSION: u32 = 1;
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Debug, Clone)]
+pub enum FastGraphCacheError {
+    NotAFastGraph,
+    VersionMismatch(u32),
+    HashMismatch(u64),
+}
+
+impl fmt::Display for FastGraphCacheError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FastGraphCacheError::NotAFastGraph => { write!(f, "file is not a cached fast graph") }
+            FastGraphCacheError::VersionMismatch(version) => {
+                write!(f, "cached fast graph has version {} but {} is required", version, FAST_GRAPH_CACHE_VERSION)
+            }
+            FastGraphCacheError::HashMismatch(hash) => {
+                write!(f, "cached fast graph was built from a different transit graph (hash {:016x})", hash)
+            }
+        }
+    }
+}
+
+impl Error for FastGraphCacheError {}
+
+/// # Fast Graph Cache
+/// Preprocessing a transit graph into contraction hierarchies is slow, so the resulting FastGraph
+/// is stored on disk, keyed by a hash of the transit graph and of the parameters used to prepare
+/// it. A changed model therefore never loads a stale graph, it simply misses the cache and the
+/// graph is rebuilt.
+#[derive(Debug, Clone)]
+pub struct FastGraphCache {
+    pub dir: PathBuf,
+    pub hierarchy_depth_factor: f32,
+}
+
+impl Default for FastGraphCache {
+    fn default() -> Self {
+        FastGraphCache {
+            dir: PathBuf::from("fast_paths"),
+            hierarchy_depth_factor: 0.1,
+        }
+    }
+}
+
+impl FastGraphCache {
+    pub fn new<P: Into<PathBuf>>(dir: P, hierarchy_depth_factor: f32) -> Self {
+        FastGraphCache {
+            dir: dir.into(),
+            hierarchy_depth_factor,
+        }
+    }
+
+    /// The path of the cache file for a given transit graph
+    pub fn path(&self, transit_graph: &TransitGraph) -> PathBuf {
+        let mut path = self.dir.join(format!("{:016x}", self.hash(transit_graph)));
+        path.set_extension("fp");
+        path
+    }
+
+    #[inline]
+    fn hash(&self, transit_graph: &TransitGraph) -> u64 {
+        hash_transit_graph(transit_graph, self.hierarchy_depth_factor)
+    }
+
+    /// Loads the FastGraph for the transit graph from the cache, building and caching it if it's
+    /// missing or unreadable
+    pub fn load_or_build(&self, transit_graph: &TransitGraph) -> FastGraph {
+        let hash = self.hash(transit_graph);
+        let path = self.path(transit_graph);
+
+        if path.exists() {
+            match load_fast_graph_from_disk(&path, hash) {
+                Ok(fast_graph) => { return fast_graph; }
+                Err(e) => { eprintln!("fast graph at {} couldn't be read from disk, rebuilding: {}", path.display(), e); }
+            }
+        }
+
+        self.build(transit_graph)
+    }
+
+    /// Builds the FastGraph for the transit graph and writes it to the cache, a failure to write
+    /// the cache isn't fatal
+    pub fn build(&self, transit_graph: &TransitGraph) -> FastGraph {
+        let fast_graph = get_fast_graph(transit_graph, &Params::new(self.hierarchy_depth_factor));
+
+        let path = self.path(transit_graph);
+        if let Err(e) = self.save(&fast_graph, self.hash(transit_graph), &path) {
+            eprintln!("fast graph couldn't be written to {}: {}", path.display(), e);
+        }
+        fast_graph
+    }
+
+    fn save(&self, fast_graph: &FastGraph, hash: u64, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::create_dir_all(&self.dir)?;
+        save_fast_graph_to_disk(fast_graph, hash, path)
+    }
+}
+
+/// Hashes the structure of a transit graph along with the preprocessing parameters, using FNV-1a
+/// so the hash is stable between builds and platforms
+pub fn hash_transit_graph(transit_graph: &TransitGraph, hierarchy_depth_factor: f32) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+
+    write(&hierarchy_depth_factor.to_le_bytes());
+    write(&(transit_graph.nodes().len() as u64).to_le_bytes());
+    write(&(transit_graph.edges().len() as u64).to_le_bytes());
+    for edge in transit_graph.edges().iter() {
+        write(&edge.start_node_index().to_le_bytes());
+        write(&edge.end_node_index().to_le_bytes());
+        write(&edge.weight().to_le_bytes());
+    }
+
+    hash
+}
+
 // struct TransitNode {
 //
 // }
@@ -19,26 +148,51 @@ use crate::TransitGraph;
 //     });
 // }
 
-pub fn get_fast_graph(transit_graph: &TransitGraph) -> FastGraph {
+pub fn get_fast_graph(transit_graph: &TransitGraph, params: &Params) -> FastGraph {
     println!("Creating Contraction Hierarchies");
     let now = Instant::now();
-    let fast_graph = preprocess_graph(transit_graph);
+    let fast_graph = preprocess_graph(transit_graph, params);
     println!("{:.6}s", now.elapsed().as_secs_f64());
     fast_graph
 }
 
-pub fn load_fast_graph_from_disk<P: AsRef<Path>>(file_name: P) -> Result<FastGraph, Box<dyn Error>> {
-    let file = File::open(file_name)?;
+/// Reads a FastGraph from disk, checking its header matches the current cache version and the
+/// hash of the transit graph it's expected to have been built from
+pub fn load_fast_graph_from_disk<P: AsRef<Path>>(file_name: P, expected_hash: u64) -> Result<FastGraph, Box<dyn Error>> {
+    let mut file = File::open(file_name)?;
+
+    let mut magic = [0u8; 4];
+    let mut version = [0u8; 4];
+    let mut hash = [0u8; 8];
+    file.read_exact(&mut magic)?;
+    if &magic != FAST_GRAPH_CACHE_MAGIC {
+        return Err(FastGraphCacheError::NotAFastGraph.into());
+    }
+    file.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != FAST_GRAPH_CACHE_VERSION {
+        return Err(FastGraphCacheError::VersionMismatch(version).into());
+    }
+    file.read_exact(&mut hash)?;
+    let hash = u64::from_le_bytes(hash);
+    if hash != expected_hash {
+        return Err(FastGraphCacheError::HashMismatch(hash).into());
+    }
+
     Ok(bincode::deserialize_from(file)?)
 }
 
-pub fn save_fast_graph_to_disk<P: AsRef<Path>>(fast_graph: &FastGraph, file_name: P) -> Result<(), Box<dyn Error>> {
-    let file = File::create(file_name)?;
+/// Writes a FastGraph to disk behind a header of the cache version and the transit graph's hash
+pub fn save_fast_graph_to_disk<P: AsRef<Path>>(fast_graph: &FastGraph, hash: u64, file_name: P) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(file_name)?;
+    file.write_all(FAST_GRAPH_CACHE_MAGIC)?;
+    file.write_all(&FAST_GRAPH_CACHE_VERSION.to_le_bytes())?;
+    file.write_all(&hash.to_le_bytes())?;
     Ok(bincode::serialize_into(file, fast_graph)?)
 }
 
 /// Creates a fast_paths Graph from the FlatBuffers TransitGraph edges data
-pub fn preprocess_graph(transit_graph: &TransitGraph) -> FastGraph {
+pub fn preprocess_graph(transit_graph: &TransitGraph, params: &Params) -> FastGraph {
     let mut input_graph = InputGraph::new();
 
     for edge in transit_graph.edges().iter() {
@@ -47,5 +201,33 @@ pub fn preprocess_graph(transit_graph: &TransitGraph) -> FastGraph {
 
     input_graph.freeze();
 
-    fast_paths::prepare(&input_graph)
+    fast_paths::prepare_with_params(&input_graph, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small_fast_graph() -> FastGraph {
+        let mut input_graph = InputGraph::new();
+        input_graph.add_edge(0, 1, 3);
+        input_graph.add_edge(1, 2, 4);
+        input_graph.freeze();
+        fast_paths::prepare(&input_graph)
+    }
+
+    #[test]
+    fn test_fast_graph_cache_header() {
+        let path = std::env::temp_dir().join("outbreak_sim_test_fast_graph_cache_header.fp");
+        let fast_graph = small_fast_graph();
+
+        save_fast_graph_to_disk(&fast_graph, 42, &path).unwrap();
+        let loaded = load_fast_graph_from_disk(&path, 42).unwrap();
+        assert_eq!(loaded.get_num_nodes(), fast_graph.get_num_nodes());
+
+        let err = load_fast_graph_from_disk(&path, 43).unwrap_err();
+        assert!(matches!(err.downcast_ref::<FastGraphCacheError>(), Some(FastGraphCacheError::HashMismatch(42))));
+
+        fs::remove_file(path).unwrap();
+    }
 }
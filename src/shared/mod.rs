@@ -1,4 +1,6 @@
 use once_cell::sync::OnceCell;
+use rand::{Error, RngCore, SeedableRng};
+use rand::rngs::StdRng;
 use serde::{Deserialize, Serialize};
 
 use crate::shared::types::TimeStep;
@@ -16,6 +18,12 @@ pub struct GlobalSimParams {
     pub cycling_speed: f32,
     /// spatial unit per time-step
     pub driving_speed: f32,
+    /// agents per time-step the roads can carry, `None` disables congestion
+    pub road_capacity: Option<f32>,
+    pub congestion_alpha: f32,
+    pub congestion_beta: f32,
+    /// seeds every random draw, see `seeded_rng`
+    pub seed: u64,
 }
 
 impl Default for GlobalSimParams {
@@ -27,6 +35,10 @@ impl Default for GlobalSimParams {
             walking_speed: 5.0 * 1000.0 * 24.0 / 48.0,
             cycling_speed: 23.5 * 1000.0 * 24.0 / 48.0,
             driving_speed: 60.0 * 1000.0 * 24.0 / 48.0,
+            road_capacity: None,
+            congestion_alpha: 0.15,
+            congestion_beta: 4.0,
+            seed: rand::random(),
         }
     }
 }
@@ -75,9 +87,111 @@ pub fn get_driving_speed() -> f32 {
     }
 }
 
+/// The separate uses of randomness in the simulation, each drawing from its own stream
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RngStream {
+    Agents,
+    HealthcareWorkers,
+    Commutes,
+    MorningCommutes,
+    Transmission,
+    Compliance,
+    Isolation,
+    Testing,
+    Tracing,
+    Hospitalisation,
+    Seeding,
+    Importation,
+}
+
+/// An RNG seeded from the run's seed, the stream, and the keys of the draws, e.g. a container and
+/// time step, so runs with the same seed make the same draws whatever order they're made in,
+/// including across threads. Unseeded if the global parameters haven't been set up.
+pub fn seeded_rng(stream: RngStream, keys: &[u64]) -> StdRng {
+    StdRng::seed_from_u64(derive_seed(stream, keys))
+}
+
+/// A `CounterRng` seeded in the same way as `seeded_rng`, for streams drawn from so often that
+/// seeding a `StdRng` for each set of keys would be a significant cost, e.g. every container update
+#[inline]
+pub fn seeded_counter_rng(stream: RngStream, keys: &[u64]) -> CounterRng {
+    CounterRng { state: derive_seed(stream, keys) }
+}
+
+#[inline]
+fn derive_seed(stream: RngStream, keys: &[u64]) -> u64 {
+    let run_seed = GLOBAL_PARAMS.get().map_or_else(rand::random, |params| params.seed);
+    keys.iter().fold(splitmix64(run_seed ^ splitmix64(stream as u64)), |seed, &key| splitmix64(seed ^ key))
+}
+
+/// A counter-based generator, each draw mixing the bits of the next value of a counter, which is
+/// free to set up but not suitable for anything needing statistical guarantees beyond sampling
+#[derive(Debug, Clone)]
+pub struct CounterRng {
+    state: u64,
+}
+
+impl RngCore for CounterRng {
+    #[inline]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    #[inline]
+    fn next_u64(&mut self) -> u64 {
+        let value = splitmix64(self.state);
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        value
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            chunk.copy_from_slice(&self.next_u64().to_le_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Mixes the bits of a value, so similar seeds give unrelated streams
+#[inline]
+fn splitmix64(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
 pub fn set_up_global_params(params: GlobalSimParams) {
     if let Err(e) = GLOBAL_PARAMS.set(params) {
         eprintln!("global parameters had already been set");
         panic!("{:?}", e);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn test_seeded_counter_rng() {
+        GLOBAL_PARAMS.get_or_init(GlobalSimParams::default);
+        let draws = |keys: &[u64]| -> Vec<f32> {
+            let mut rng = seeded_counter_rng(RngStream::Transmission, keys);
+            (0..10_000).map(|_| rng.gen::<f32>()).collect()
+        };
+        assert_eq!(draws(&[1, 2]), draws(&[1, 2]));
+        assert_ne!(draws(&[1, 2]), draws(&[2, 1]));
+
+        let mean = draws(&[3]).iter().sum::<f32>() / 10_000.0;
+        assert!((mean - 0.5).abs() < 0.01);
+        let mut bytes = [0u8; 11];
+        seeded_counter_rng(RngStream::Transmission, &[3]).fill_bytes(&mut bytes);
+        assert!(bytes.iter().any(|&byte| byte != 0));
+    }
+}
@@ -0,0 +1,18 @@
+use std::fs;
+
+use outbreak_sim::SimBuilder;
+
+// the model is checked before the global parameters are set, so a process can try more than one
+#[test]
+fn test_unreadable_models_are_errors() {
+    let dir = std::env::temp_dir().join(format!("outbreak_sim_build_errors_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let missing = SimBuilder::new(&dir, "missing").build();
+    assert!(missing.err().unwrap().to_string().contains("couldn't find file"));
+
+    fs::write(dir.join("corrupt.txt"), b"not a model").unwrap();
+    assert!(SimBuilder::new(&dir, "corrupt").build().is_err());
+
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use outbreak_sim::{Sim, SimError};
+use outbreak_sim::disease::{Transmission, Uniform};
+use outbreak_sim::reporting::{ObserverInterval, SimObserver};
+use outbreak_sim::shared::types::TimeStep;
+
+/// The calls an observer has been given
+#[derive(Debug, Default, PartialEq)]
+struct Calls {
+    time_steps: Vec<TimeStep>,
+    days: Vec<u32>,
+    end: Option<TimeStep>,
+}
+
+struct RecordingObserver {
+    calls: Arc<Mutex<Calls>>,
+}
+
+impl SimObserver<Uniform> for RecordingObserver {
+    fn on_time_step(&mut self, _sim: &Sim<Uniform>, time_step: TimeStep) -> Result<(), Box<dyn Error>> {
+        self.calls.lock().unwrap().time_steps.push(time_step);
+        Ok(())
+    }
+
+    fn on_day(&mut self, _sim: &Sim<Uniform>, day: u32, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.calls.lock().unwrap().days.push(day);
+        Ok(())
+    }
+
+    fn on_end(&mut self, _sim: &Sim<Uniform>, time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        self.calls.lock().unwrap().end = Some(time_step);
+        Ok(())
+    }
+}
+
+struct FailingObserver;
+
+impl SimObserver<Uniform> for FailingObserver {
+    fn on_end(&mut self, _sim: &Sim<Uniform>, _time_step: TimeStep, _transmissions: &[Transmission]) -> Result<(), Box<dyn Error>> {
+        Err("couldn't write the report".into())
+    }
+}
+
+fn record(sim: &mut Sim<Uniform>, interval: ObserverInterval) -> Arc<Mutex<Calls>> {
+    let calls = Arc::new(Mutex::new(Calls::default()));
+    sim.add_observer(Box::new(RecordingObserver { calls: calls.clone() }), interval);
+    calls
+}
+
+fn is_send_and_sync<T: Send + Sync>() {}
+
+// the global parameters can only be set once per process, so there's a single sim for every check
+#[test]
+fn test_observers_are_shown_the_sim_at_their_intervals() {
+    is_send_and_sync::<Sim<Uniform>>();
+    let mut sim = outbreak_sim::SimBuilder::new(Path::new("python/synthetic_environments/examples"), "isle_of_dogs")
+        .time_steps_per_day(4)
+        .sim_length_days(Some(4))
+        .build()
+        .unwrap();
+    let every_time_step = record(&mut sim, ObserverInterval::default());
+    let every_other_time_step = record(&mut sim, ObserverInterval::TimeSteps(2));
+    let every_third_time_step = record(&mut sim, ObserverInterval::TimeSteps(3));
+    let every_other_day = record(&mut sim, ObserverInterval::Days(2));
+    // observers aren't cloned along with the sim
+    let _clone = sim.clone();
+
+    let mut time_step = 0;
+    loop {
+        match sim.update(time_step) {
+            Ok(()) => { time_step += 1; }
+            Err(SimError::EndOfSimulation) => { break; }
+            Err(e) => { panic!("{}", e); }
+        }
+    }
+    assert_eq!(time_step, 16);
+    sim.add_observer(Box::new(FailingObserver), ObserverInterval::default());
+    assert!(matches!(sim.finish(time_step), Err(SimError::Observer(_))));
+
+    assert_eq!(*every_time_step.lock().unwrap(), Calls { time_steps: (0..16).collect(), days: vec![0, 1, 2, 3], end: Some(16) });
+    assert_eq!(*every_other_time_step.lock().unwrap(), Calls { time_steps: (0..16).step_by(2).collect(), days: vec![0, 1, 2, 3], end: Some(16) });
+    // only the first time steps of days 0 and 3 are multiples of 3
+    assert_eq!(*every_third_time_step.lock().unwrap(), Calls { time_steps: (0..16).step_by(3).collect(), days: vec![0, 3], end: Some(16) });
+    assert_eq!(*every_other_day.lock().unwrap(), Calls { time_steps: vec![0, 8], days: vec![0, 2], end: Some(16) });
+}
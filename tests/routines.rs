@@ -0,0 +1,84 @@
+use std::path::Path;
+
+use outbreak_sim::Sim;
+use outbreak_sim::agents::Location;
+use outbreak_sim::disease::Uniform;
+use outbreak_sim::routing::{DirectRoutingType, RoutingType};
+use outbreak_sim::shared::get_time_steps_per_day;
+use outbreak_sim::shared::types::TimeStep;
+
+/// Updates the sim over the given time steps, checking every agent is in exactly one container or
+/// in transit after each
+fn update(sim: &mut Sim<Uniform>, time_steps: std::ops::Range<TimeStep>) {
+    for time_step in time_steps {
+        sim.update(time_step).unwrap();
+        if let Err(e) = sim.containers.verify_locations(&sim.agents.locations) {
+            panic!("{}", e);
+        }
+    }
+}
+
+/// The agents at their occupational container
+fn at_work(sim: &Sim<Uniform>) -> Vec<u32> {
+    (0..sim.agents.num_agents)
+        .filter(|&agent_idx| {
+            sim.agents.occupational_container[agent_idx as usize]
+                .is_some_and(|occupational_idx| sim.agent_location(agent_idx) == Location::InContainer(occupational_idx.get()))
+        })
+        .collect()
+}
+
+// the global parameters can only be set once per process, so there's a single sim for every check
+#[test]
+fn test_interrupted_routines() {
+    // slow enough for commutes to take more than one time step
+    let mut sim = outbreak_sim::SimBuilder::new(Path::new("python/synthetic_environments/examples"), "isle_of_dogs")
+        .driving_speed_kph(3.0)
+        .build()
+        .unwrap();
+    let tspd = get_time_steps_per_day();
+    let midday = tspd / 2;
+
+    // drivers taken out of transit are taken off the road too
+    let morning = tspd / 3;
+    update(&mut sim, 0..morning);
+    let drivers: Vec<u32> = (0..sim.agents.num_agents).filter(|&agent_idx| match sim.agent_location(agent_idx) {
+        Location::InTransit(journey) => {
+            journey.routing_type == RoutingType::Direct(DirectRoutingType::Driving) && journey.arrival_time_step > morning + 1
+        }
+        _ => { false }
+    }).collect();
+    assert!(drivers.len() >= 2);
+    let mut interrupted = sim.clone();
+    interrupted.relocate(drivers[0], interrupted.agents.household_container[drivers[0] as usize], morning);
+    interrupted.remove(drivers[1], morning);
+    update(&mut sim, morning..morning + 2);
+    update(&mut interrupted, morning..morning + 2);
+    assert_eq!(sim.congestion.volume(), interrupted.congestion.volume() + 2);
+
+    update(&mut sim, morning + 2..midday);
+    let workers = at_work(&sim);
+    assert!(workers.len() >= 4);
+    let (sent_home, relocated, removed) = (workers[0], workers[1], workers[2]);
+    let household_of = |sim: &Sim<Uniform>, agent_idx: u32| sim.agents.household_container[agent_idx as usize];
+
+    // agents sent home go straight there, and carry on with their routine the next morning
+    assert!(sim.send_home(sent_home, midday).is_some());
+    let elsewhere = household_of(&sim, workers[3]);
+    sim.relocate(relocated, elsewhere, midday);
+    sim.remove(removed, midday);
+    update(&mut sim, midday..tspd);
+    assert_eq!(sim.agent_location(sent_home), Location::InContainer(household_of(&sim, sent_home)));
+    // nothing's queued for relocated agents, so they stay where they were put
+    assert_eq!(sim.agent_location(relocated), Location::InContainer(elsewhere));
+    assert_eq!(sim.agent_location(removed), Location::Removed);
+
+    assert!(sim.resume_routine(relocated, tspd).is_some());
+    update(&mut sim, tspd..tspd + midday);
+    let workers = at_work(&sim);
+    assert!(workers.contains(&sent_home));
+    assert!(!workers.contains(&relocated));
+    assert_eq!(sim.agent_location(removed), Location::Removed);
+    update(&mut sim, tspd + midday..2 * tspd + midday);
+    assert!(at_work(&sim).contains(&relocated));
+}